@@ -0,0 +1,183 @@
+//! Merging records that mean the same set.
+
+use std::collections::HashMap;
+
+use crate::language::{ENGLISH, resolve_forms};
+use crate::pronouns::{PronounRecord, PronounSet};
+
+/// What makes two records the same, regardless of how they were written.
+#[derive(PartialEq, Eq, Hash)]
+enum RecordKey {
+    /// Every form, given or inferred, in the set's language
+    Defined {
+        /// The `lang=` tag, which is `en` for sets without one
+        language: String,
+        forms: [Option<String>; 5],
+    },
+    Any,
+    None,
+    /// A record with only a comment
+    Comment(Option<String>),
+}
+
+impl RecordKey {
+    fn new(record: &PronounRecord) -> Self {
+        match &record.set {
+            Some(set) => RecordKey::set(set),
+            None => RecordKey::Comment(record.comment.clone()),
+        }
+    }
+
+    fn set(set: &PronounSet) -> Self {
+        match set {
+            PronounSet::Defined { definition, .. } => RecordKey::Defined {
+                language: set.language().unwrap_or(ENGLISH.code).to_owned(),
+                forms: resolve_forms(set.language(), definition)
+                    .map(|(_, resolved)| resolved.value.map(String::from)),
+            },
+            PronounSet::Any => RecordKey::Any,
+            PronounSet::None => RecordKey::None,
+        }
+    }
+}
+
+/// Whether both sets mean the same, ignoring their tags other than the language, the way
+/// [`dedup_records`] compares them.
+pub(crate) fn same_set(a: &PronounSet, b: &PronounSet) -> bool {
+    RecordKey::set(a) == RecordKey::set(b)
+}
+
+/// Merges the records that mean the same set, such as `she/her` and
+/// `she/her/her/hers/herself`, keeping the first of them where it is.
+///
+/// The merged record has the tags of all of them, and their comments joined with `; `.
+///
+/// ```
+/// let records = ["she/her", "they/them", "she/her/her/hers/herself; preferred # hi"]
+///     .map(|record| podns::parse_record(record).unwrap());
+///
+/// let records = podns::dedup_records(records);
+/// assert_eq!(records.len(), 2);
+/// assert_eq!(
+///     records[0].to_string(),
+///     "she/her/her/hers/herself; preferred # hi"
+/// );
+/// ```
+pub fn dedup_records(records: impl IntoIterator<Item = PronounRecord>) -> Vec<PronounRecord> {
+    // each record with its comments, which are only joined once all of them are known
+    let mut merged: Vec<(PronounRecord, Vec<String>)> = Vec::new();
+    let mut seen: HashMap<RecordKey, usize> = HashMap::new();
+
+    for mut record in records {
+        let key = RecordKey::new(&record);
+        let comment = record.comment.take();
+        let index = match seen.get(&key) {
+            Some(&index) => {
+                merge_tags(&mut merged[index].0, record);
+                index
+            }
+            None => {
+                seen.insert(key, merged.len());
+                merged.push((record, Vec::new()));
+                merged.len() - 1
+            }
+        };
+
+        let comments = &mut merged[index].1;
+        if let Some(comment) = comment
+            && !comments.contains(&comment)
+        {
+            comments.push(comment);
+        }
+    }
+
+    merged
+        .into_iter()
+        .map(|(mut record, comments)| {
+            if !comments.is_empty() {
+                record.comment = Some(comments.join("; "));
+            }
+            record
+        })
+        .collect()
+}
+
+/// Adds the tags of `other` to `record`, which means the same set.
+fn merge_tags(record: &mut PronounRecord, other: PronounRecord) {
+    if let (
+        Some(PronounSet::Defined { tags, .. }),
+        Some(PronounSet::Defined {
+            tags: other_tags, ..
+        }),
+    ) = (&mut record.set, other.set)
+    {
+        for tag in other_tags {
+            if !tags.contains(&tag) {
+                tags.push(tag);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_record;
+    use crate::pronouns::PronounTag;
+
+    fn dedup(records: &[&str]) -> Vec<PronounRecord> {
+        dedup_records(records.iter().map(|record| parse_record(record).unwrap()))
+    }
+
+    #[test]
+    fn test_equivalent_sets() {
+        let records = dedup(&["she/her", "she/her/her/hers/herself; preferred", "he/him"]);
+        assert_eq!(records.len(), 2);
+        assert!(matches!(
+            &records[0].set,
+            Some(PronounSet::Defined { tags, .. }) if tags.contains(&PronounTag::Preferred)
+        ));
+        // the first record is kept as it was written
+        assert_eq!(records[0].canonical(), "she/her; preferred");
+    }
+
+    #[test]
+    fn test_different_sets() {
+        // unknown forms aren't the same as given ones
+        assert_eq!(dedup(&["xe/xem", "xe/xem/xyr"]).len(), 2);
+        assert_eq!(dedup(&["she/her", "she/her/her/hers/herselves"]).len(), 2);
+        // neither are sets in other languages
+        assert_eq!(dedup(&["sie/sie", "sie/sie; lang=de"]).len(), 2);
+        // but sets without a language are English
+        assert_eq!(dedup(&["she/her", "she/her; lang=en"]).len(), 1);
+        // and the forms of other languages aren't inferred from English
+        assert_eq!(
+            dedup(&["she/her; lang=fr", "she/her/her; lang=fr"]).len(),
+            2
+        );
+    }
+
+    #[test]
+    fn test_merged_comments() {
+        let records = dedup(&["* # a", "*", "* # b", "* # a", "# c", "# c"]);
+        assert_eq!(
+            records
+                .iter()
+                .map(PronounRecord::canonical)
+                .collect::<Vec<_>>(),
+            vec!["* # a; b", "# c"]
+        );
+
+        // comments are compared whole, even when they contain the separator
+        let records = dedup(&["* # hi; bye", "* # hi", "* # hi; bye"]);
+        assert_eq!(records[0].canonical(), "* # hi; bye; hi");
+    }
+
+    #[test]
+    fn test_hash_consistent_with_eq() {
+        let records = ["she/her", "she/her", "she/her/her", "she/her # hi"]
+            .map(|record| parse_record(record).unwrap());
+        let set: std::collections::HashSet<_> = records.into_iter().collect();
+        assert_eq!(set.len(), 3);
+    }
+}
@@ -1,125 +1,358 @@
 use std::io;
 
-use resolve::record::Txt;
+use crate::lookup::{InvalidTxtRecord, TxtLookup};
 
-pub fn query_txt(domain: &str) -> io::Result<Vec<String>> {
-    let config = {
-        #[cfg(windows)]
-        {
-            windows::default_dns_config()?
-        }
-        #[cfg(not(windows))]
-        {
-            resolve::DnsConfig::load_default()?
-        }
-    };
+#[cfg(feature = "doh")]
+pub mod doh;
+#[cfg(feature = "dot")]
+pub mod dot;
+#[cfg(feature = "dns_server")]
+pub mod server;
+#[cfg(feature = "dns_resolve")]
+mod system;
+pub(crate) mod wire;
+
+#[cfg(feature = "dns_server")]
+pub use server::DnsServer;
+#[cfg(feature = "dns_resolve")]
+pub use system::{SystemResolver, query_txt, query_txt_raw};
+
+/// Encodes a recursive query for the TXT records of `name`, in DNS wire format.
+///
+/// The query asks the resolver to report whether the answer was validated with DNSSEC.
+pub fn encode_txt_query(name: &str, id: u16) -> io::Result<Vec<u8>> {
+    wire::encode_query(name, id)
+}
+
+/// Decodes the TXT records in a DNS wire format response, such as one to [`encode_txt_query`].
+///
+/// Fails with [`io::ErrorKind::NotFound`] if the name does not exist. Records that are not
+/// valid UTF-8 are in [`TxtLookup::invalid`], rather than failing the whole lookup.
+pub fn decode_txt_response(response: &[u8]) -> io::Result<TxtLookup> {
+    let response = wire::decode_response(response)?;
+    let name = txt_question(&response)?;
 
-    let resolver = resolve::DnsResolver::new(config)?;
+    let (records, invalid) = split_text(txt_records(&response)?);
 
-    let results = resolver.resolve_record::<Txt>(domain)?;
+    // the records may only be cached for as long as the shortest lived answer
+    let ttl = response
+        .answers
+        .iter()
+        .filter(|rr| rr.rtype == wire::TYPE_TXT && rr.rclass == wire::CLASS_IN)
+        .map(|rr| rr.ttl)
+        .min();
 
-    Ok(results
-        .into_iter()
-        .filter_map(|txt| String::from_utf8(txt.data).ok())
-        .collect::<Vec<String>>())
+    Ok(TxtLookup {
+        name,
+        records,
+        authenticated: response.authenticated,
+        ttl,
+        invalid,
+    })
 }
 
-#[cfg(windows)]
-mod windows {
-    use std::io;
+/// Decodes a response to a query for `name`, making sure it answers that query.
+#[cfg(any(feature = "dns_resolve", feature = "doh", feature = "dot"))]
+fn lookup_from_response(name: &str, response: &[u8]) -> io::Result<TxtLookup> {
+    let mut lookup = decode_txt_response(response)?;
+    check_question(name, &lookup.name)?;
 
-    use resolve::DnsConfig;
+    // keep the name as it was asked for
+    lookup.name = name.to_owned();
+    Ok(lookup)
+}
 
-    pub(super) fn default_dns_config() -> io::Result<DnsConfig> {
-        use windows::Win32::Foundation::ERROR_BUFFER_OVERFLOW;
-        use windows::Win32::NetworkManagement::IpHelper::GetNetworkParams;
+/// Fails unless `question`, the name a response is for, is `name`.
+#[cfg(any(feature = "dns_resolve", feature = "doh", feature = "dot"))]
+fn check_question(name: &str, question: &str) -> io::Result<()> {
+    if !question.eq_ignore_ascii_case(name.strip_suffix('.').unwrap_or(name)) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "response does not match the query",
+        ));
+    }
+    Ok(())
+}
 
-        // First call to get the required buffer size
-        let mut buf_len: u32 = 0;
-        let result = unsafe { GetNetworkParams(None, &mut buf_len) };
+/// The name in the only question of a response, which must be a TXT query.
+fn txt_question(response: &wire::Response) -> io::Result<String> {
+    match response.questions.as_slice() {
+        [question] if question.qtype == wire::TYPE_TXT && question.qclass == wire::CLASS_IN => {
+            Ok(question.name.clone())
+        }
+        _ => Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "response is not for a TXT query",
+        )),
+    }
+}
 
-        if result != ERROR_BUFFER_OVERFLOW {
+/// Extracts the TXT records from the answer section of a response.
+fn txt_records(response: &wire::Response) -> io::Result<Vec<Vec<u8>>> {
+    match response.rcode {
+        wire::Rcode::NoError => {}
+        wire::Rcode::NameError => {
             return Err(io::Error::new(
-                io::ErrorKind::Other,
-                format!("GetNetworkParams failed"),
+                io::ErrorKind::NotFound,
+                wire::Rcode::NameError.to_string(),
             ));
         }
+        rcode => return Err(io::Error::other(rcode.to_string())),
+    }
 
-        // Allocate buffer and call again
-        let mut buffer = vec![0u8; buf_len as usize];
-        let fixed_info = buffer.as_mut_ptr()
-            as *mut windows::Win32::NetworkManagement::IpHelper::FIXED_INFO_W2KSP1;
+    if response.truncated {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "response was truncated",
+        ));
+    }
 
-        let result = unsafe { GetNetworkParams(Some(fixed_info), &mut buf_len) };
+    response
+        .answers
+        .iter()
+        .filter(|rr| rr.rtype == wire::TYPE_TXT && rr.rclass == wire::CLASS_IN)
+        // RFC 7208 section 3.3 - the strings are concatenated together without adding spaces
+        .map(|rr| wire::decode_txt_rdata(&rr.rdata).map(|chunks| chunks.concat()))
+        .collect()
+}
 
-        if result.0 != 0 {
-            return Err(io::Error::new(
-                io::ErrorKind::Other,
-                format!("GetNetworkParams failed"),
-            ));
+/// Splits TXT records into the ones that are text, and the ones that aren't valid UTF-8.
+fn split_text(records: Vec<Vec<u8>>) -> (Vec<String>, Vec<InvalidTxtRecord>) {
+    let mut text = Vec::new();
+    let mut invalid = Vec::new();
+
+    for data in records {
+        match String::from_utf8(data) {
+            Ok(record) => text.push(record),
+            Err(e) => invalid.push(InvalidTxtRecord {
+                data: e.into_bytes(),
+            }),
         }
+    }
 
-        // Extract DNS servers
-        let mut nameservers: Vec<std::net::IpAddr> = Vec::new();
-        unsafe {
-            let fixed = &*fixed_info;
+    (text, invalid)
+}
 
-            // Add primary DNS server
-            let dns_addr = std::ffi::CStr::from_ptr(
-                fixed.DnsServerList.IpAddress.String.as_ptr() as *const i8
-            )
-            .to_string_lossy();
+/// A message id for a query, which should be hard to guess to make spoofing responses harder.
+#[cfg(any(feature = "dns_resolve", feature = "dot"))]
+fn random_id() -> u16 {
+    use std::hash::{BuildHasher, RandomState};
 
-            if !dns_addr.is_empty() && dns_addr != "0.0.0.0" {
-                if let Ok(addr) = dns_addr.parse::<std::net::IpAddr>() {
-                    nameservers.push(addr);
-                }
-            }
+    // the hasher is seeded with random keys
+    RandomState::new().hash_one(std::time::SystemTime::now()) as u16
+}
 
-            // Add additional DNS servers from linked list
-            let mut current = fixed.DnsServerList.Next;
-            while !current.is_null() {
-                let dns_addr =
-                    std::ffi::CStr::from_ptr((*current).IpAddress.String.as_ptr() as *const i8)
-                        .to_string_lossy();
-
-                if !dns_addr.is_empty() && dns_addr != "0.0.0.0" {
-                    if let Ok(addr) = dns_addr.parse::<std::net::IpAddr>() {
-                        nameservers.push(addr);
-                    }
-                }
-                current = (*current).Next;
-            }
-        }
+/// Sends a query over a stream connection, where messages are prefixed with their length.
+#[cfg(any(feature = "dns_resolve", feature = "dot"))]
+fn exchange_tcp<S: io::Read + io::Write>(
+    stream: &mut S,
+    id: u16,
+    query: &[u8],
+) -> io::Result<Vec<u8>> {
+    let len = u16::try_from(query.len())
+        .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "query is too long"))?;
 
-        if nameservers.is_empty() {
-            return Err(io::Error::new(
-                io::ErrorKind::NotFound,
-                "No DNS servers found",
-            ));
-        }
+    let mut message = Vec::with_capacity(query.len() + 2);
+    message.extend_from_slice(&len.to_be_bytes());
+    message.extend_from_slice(query);
+    stream.write_all(&message)?;
+    stream.flush()?;
 
-        // convert IpAddr to SocketAddr with default port 53
-        let nameservers: Vec<std::net::SocketAddr> = nameservers
-            .into_iter()
-            .map(|ip| std::net::SocketAddr::new(ip, 53))
-            .collect();
+    let mut len = [0; 2];
+    stream.read_exact(&mut len)?;
+    let mut response = vec![0; u16::from_be_bytes(len) as usize];
+    stream.read_exact(&mut response)?;
 
-        Ok(DnsConfig::with_name_servers(nameservers))
+    if response.len() < wire::HEADER_LEN || response[..2] != id.to_be_bytes() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "response does not match the query",
+        ));
     }
+
+    Ok(response)
 }
 
 #[cfg(test)]
-mod tests {
+pub(crate) mod tests {
+    use std::io;
+
+    use super::{decode_txt_response, encode_txt_query, txt_records, wire};
+
+    // builds a response to a TXT query for `pronouns.example`, with one answer per rdata
+    pub(crate) fn txt_response(rdatas: &[&[u8]]) -> Vec<u8> {
+        let mut msg = Vec::new();
+        msg.extend_from_slice(&0x1234u16.to_be_bytes()); // id
+        msg.extend_from_slice(&[0x81, 0x80]); // response, recursion desired + available, no error
+        msg.extend_from_slice(&1u16.to_be_bytes()); // qdcount
+        msg.extend_from_slice(&(rdatas.len() as u16).to_be_bytes()); // ancount
+        msg.extend_from_slice(&[0, 0, 0, 0]); // nscount, arcount
+
+        // question
+        msg.extend_from_slice(b"\x08pronouns\x07example\x00");
+        msg.extend_from_slice(&[0x00, 0x10, 0x00, 0x01]);
+
+        for rdata in rdatas {
+            // pointer to the name in the question
+            msg.extend_from_slice(&[0xc0, 0x0c]);
+            msg.extend_from_slice(&[0x00, 0x10, 0x00, 0x01]);
+            msg.extend_from_slice(&300u32.to_be_bytes());
+            msg.extend_from_slice(&(rdata.len() as u16).to_be_bytes());
+            msg.extend_from_slice(rdata);
+        }
+
+        msg
+    }
+
+    // A response from a recursive resolver to a TXT query for `pronouns.kinda.red`, with
+    // the id set to 0 as RFC 8484 asks for. The answer has two records, one of them split over
+    // two character-strings, and the additional section holds an EDNS OPT record.
+    pub(crate) const KINDA_RED_RESPONSE: &[u8] = b"\
+        \x00\x00\x81\xa0\x00\x01\x00\x02\x00\x00\x00\x01\
+        \x08pronouns\x05kinda\x03red\x00\x00\x10\x00\x01\
+        \xc0\x0c\x00\x10\x00\x01\x00\x00\x0e\x10\x00\x13\
+        \x12she/her; preferred\
+        \xc0\x0c\x00\x10\x00\x01\x00\x00\x0e\x10\x00\x1c\
+        \x0cthey/them # \x0eonly sometimes\
+        \x00\x00\x29\x04\xd0\x00\x00\x00\x00\x00\x00";
+
+    // A response for a name that does not exist, with the SOA record of the zone in the
+    // authority section.
+    const NXDOMAIN_RESPONSE: &[u8] = b"\
+        \x00\x00\x81\x83\x00\x01\x00\x00\x00\x01\x00\x00\
+        \x08pronouns\x07example\x03com\x00\x00\x10\x00\x01\
+        \xc0\x15\x00\x06\x00\x01\x00\x00\x0e\x10\x00\x2c\
+        \x02ns\x04icann\x03org\x00\x03noc\x03dns\xc0\x35\
+        \x78\x9a\x6c\x30\x00\x00\x1c\x20\x00\x00\x0e\x10\x00\x12\x75\x00\x00\x00\x0e\x10";
+
+    // A response that follows a CNAME from `pronouns.example` to `pronouns.example.net`.
+    const CNAME_RESPONSE: &[u8] = b"\
+        \xbe\xef\x81\x80\x00\x01\x00\x02\x00\x00\x00\x00\
+        \x08pronouns\x07example\x00\x00\x10\x00\x01\
+        \xc0\x0c\x00\x05\x00\x01\x00\x00\x01\x2c\x00\x16\
+        \x08pronouns\x07example\x03net\x00\
+        \xc0\x2e\x00\x10\x00\x01\x00\x00\x01\x2c\x00\x07\
+        \x06ze/hir";
+
+    fn decode_answers(response: &[u8]) -> Vec<Vec<u8>> {
+        txt_records(&wire::decode_response(response).unwrap()).expect("Failed to decode response")
+    }
+
+    #[test]
+    fn test_single_string() {
+        let response = txt_response(&[b"\x12she/her; preferred"]);
+        assert_eq!(
+            decode_answers(&response),
+            vec![b"she/her; preferred".to_vec()]
+        );
+    }
+
+    #[test]
+    fn test_multiple_strings_are_joined() {
+        // a 300 byte comment has to be split over two character-strings
+        let record = format!("they/them # {}", "a".repeat(289));
+        let (first, second) = record.as_bytes().split_at(255);
+
+        let mut rdata = vec![255u8];
+        rdata.extend_from_slice(first);
+        rdata.push(second.len() as u8);
+        rdata.extend_from_slice(second);
+
+        let response = txt_response(&[&rdata]);
+        assert_eq!(decode_answers(&response), vec![record.into_bytes()]);
+    }
+
+    #[test]
+    fn test_no_separator_between_strings() {
+        let response = txt_response(&[b"\x04she/\x03her", b"\x00\x01*"]);
+        assert_eq!(
+            decode_answers(&response),
+            vec![b"she/her".to_vec(), b"*".to_vec()]
+        );
+    }
+
+    #[test]
+    fn test_truncated_string() {
+        // length octet claims more data than the rdata holds
+        let response = txt_response(&[b"\x10she/her"]);
+        assert!(decode_txt_response(&response).is_err());
+    }
+
+    #[test]
+    fn test_invalid_utf8() {
+        // the valid record is kept, and the invalid one reported on its own
+        let response = txt_response(&[b"\x07she/h\xffr", b"\x09they/them"]);
+
+        let lookup = decode_txt_response(&response).unwrap();
+        assert_eq!(lookup.records, vec!["they/them"]);
+        assert_eq!(lookup.invalid.len(), 1);
+        assert_eq!(lookup.invalid[0].data, b"she/h\xffr");
+        assert_eq!(
+            lookup.invalid[0].to_string(),
+            "TXT record is not valid UTF-8: \"she/h\u{fffd}r\""
+        );
+    }
+
+    #[test]
+    fn test_name_error() {
+        let mut response = txt_response(&[]);
+        response[3] |= 0x03; // NXDOMAIN
+        let err = decode_txt_response(&response).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::NotFound);
+
+        let err = decode_txt_response(NXDOMAIN_RESPONSE).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::NotFound);
+    }
 
     #[test]
-    fn test_query_txt() {
-        let domain = "pronouns.kinda.red";
-        let results = crate::dns::query_txt(domain).expect("Failed to query TXT records");
-        assert!(
-            results.contains(&"she/her; preferred".to_string()),
-            "Expected pronoun record not found, got: {:?}",
-            results
+    fn test_server_failure() {
+        let mut response = txt_response(&[]);
+        response[3] |= 0x02; // SERVFAIL
+        let err = decode_txt_response(&response).unwrap_err();
+        assert_eq!(err.to_string(), "server failure");
+    }
+
+    #[test]
+    fn test_resolver_response() {
+        let lookup = decode_txt_response(KINDA_RED_RESPONSE).unwrap();
+        assert_eq!(lookup.name, "pronouns.kinda.red");
+        assert_eq!(
+            lookup.records,
+            vec!["she/her; preferred", "they/them # only sometimes"]
         );
+        assert!(lookup.authenticated);
+        assert_eq!(lookup.ttl, Some(3600));
+    }
+
+    #[test]
+    fn test_cname_response() {
+        let lookup = decode_txt_response(CNAME_RESPONSE).unwrap();
+        assert_eq!(lookup.name, "pronouns.example");
+        assert_eq!(lookup.records, vec!["ze/hir"]);
+        assert!(!lookup.authenticated);
+    }
+
+    #[test]
+    fn test_encode_query() {
+        let query = encode_txt_query("pronouns.example", 0).unwrap();
+        assert_eq!(&query[..4], b"\x00\x00\x01\x20");
+        assert!(decode_txt_response(&query).is_err());
+    }
+
+    #[test]
+    fn test_malformed_responses_do_not_panic() {
+        for response in [KINDA_RED_RESPONSE, NXDOMAIN_RESPONSE, CNAME_RESPONSE] {
+            for len in 0..response.len() {
+                let _ = decode_txt_response(&response[..len]);
+            }
+
+            for i in 0..response.len() {
+                for byte in [0x00, 0x01, 0x3f, 0x40, 0x80, 0xc0, 0xff] {
+                    let mut mutated = response.to_vec();
+                    mutated[i] = byte;
+                    let _ = decode_txt_response(&mutated);
+                }
+            }
+        }
     }
 }
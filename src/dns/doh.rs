@@ -0,0 +1,399 @@
+//! DNS-over-HTTPS (RFC 8484) lookups.
+
+use std::io::{self, Read};
+use std::time::Duration;
+
+use serde_json::Value;
+
+use super::{encode_txt_query, lookup_from_response, split_text, wire};
+use crate::lookup::{TxtLookup, TxtResolver};
+
+const DNS_MESSAGE: &str = "application/dns-message";
+const DNS_JSON: &str = "application/dns-json";
+
+/// Largest response we accept, the same as the largest DNS message.
+const RESPONSE_LIMIT: u64 = 0xffff;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DohFormat {
+    /// DNS wire format, as specified by RFC 8484
+    #[default]
+    Wire,
+    /// The JSON format served by e.g. Google and Cloudflare at `/resolve` and `/dns-query`
+    Json,
+}
+
+/// Looks up TXT records with DNS-over-HTTPS, against a server such as
+/// `https://cloudflare-dns.com/dns-query`.
+///
+/// Whether an answer is `authenticated` is taken from the AD bit set by the server.
+#[derive(Debug, Clone)]
+pub struct DohResolver {
+    url: String,
+    format: DohFormat,
+    agent: ureq::Agent,
+}
+
+impl DohResolver {
+    pub fn new(url: impl Into<String>) -> Self {
+        DohResolver {
+            url: url.into(),
+            format: DohFormat::default(),
+            agent: ureq::AgentBuilder::new()
+                .timeout(Duration::from_secs(5))
+                .build(),
+        }
+    }
+
+    pub fn with_format(mut self, format: DohFormat) -> Self {
+        self.format = format;
+        self
+    }
+
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.agent = ureq::AgentBuilder::new().timeout(timeout).build();
+        self
+    }
+
+    fn lookup_wire(&self, name: &str) -> io::Result<TxtLookup> {
+        // RFC 8484 section 4.1 - the id should be 0 to make responses cacheable
+        let query = encode_txt_query(name, 0)?;
+
+        let response = self
+            .agent
+            .post(&self.url)
+            .set("Content-Type", DNS_MESSAGE)
+            .set("Accept", DNS_MESSAGE)
+            .send_bytes(&query)
+            .map_err(http_error)?;
+
+        if response.content_type() != DNS_MESSAGE {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("unexpected content type {}", response.content_type()),
+            ));
+        }
+
+        let mut body = Vec::new();
+        response
+            .into_reader()
+            .take(RESPONSE_LIMIT)
+            .read_to_end(&mut body)?;
+
+        lookup_from_response(name, &body)
+    }
+
+    fn lookup_json(&self, name: &str) -> io::Result<TxtLookup> {
+        let response = self
+            .agent
+            .get(&self.url)
+            .query("name", name)
+            .query("type", "TXT")
+            .set("Accept", DNS_JSON)
+            .call()
+            .map_err(http_error)?;
+
+        let mut body = Vec::new();
+        response
+            .into_reader()
+            .take(RESPONSE_LIMIT)
+            .read_to_end(&mut body)?;
+
+        let json: Value = serde_json::from_slice(&body)?;
+
+        match json["Status"].as_u64() {
+            Some(0) => {}
+            Some(3) => {
+                return Err(io::Error::new(io::ErrorKind::NotFound, "no such name"));
+            }
+            Some(status) => {
+                return Err(io::Error::other(format!(
+                    "server responded with status {}",
+                    status
+                )));
+            }
+            None => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "response has no status",
+                ));
+            }
+        }
+
+        if json["TC"].as_bool() == Some(true) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "response was truncated",
+            ));
+        }
+
+        let answers = match &json["Answer"] {
+            Value::Array(answers) => answers.as_slice(),
+            _ => &[],
+        };
+
+        let txt_answers = answers
+            .iter()
+            .filter(|answer| answer["type"].as_u64() == Some(u64::from(wire::TYPE_TXT)));
+
+        let records = txt_answers
+            .clone()
+            .map(|answer| {
+                let data = answer["data"].as_str().ok_or_else(|| {
+                    io::Error::new(io::ErrorKind::InvalidData, "TXT answer has no data")
+                })?;
+                parse_json_txt_data(data)
+            })
+            .collect::<io::Result<Vec<Vec<u8>>>>()?;
+        let (records, invalid) = split_text(records);
+
+        let ttl = txt_answers
+            .filter_map(|answer| answer["TTL"].as_u64())
+            .min()
+            .map(|ttl| u32::try_from(ttl).unwrap_or(u32::MAX));
+
+        Ok(TxtLookup {
+            name: name.to_owned(),
+            records,
+            authenticated: json["AD"].as_bool().unwrap_or(false),
+            ttl,
+            invalid,
+        })
+    }
+}
+
+impl TxtResolver for DohResolver {
+    fn lookup_txt(&self, name: &str) -> io::Result<TxtLookup> {
+        match self.format {
+            DohFormat::Wire => self.lookup_wire(name),
+            DohFormat::Json => self.lookup_json(name),
+        }
+    }
+}
+
+fn http_error(e: ureq::Error) -> io::Error {
+    match e {
+        ureq::Error::Status(code, _) => {
+            io::Error::other(format!("DoH server responded with HTTP {}", code))
+        }
+        ureq::Error::Transport(transport) => io::Error::other(transport),
+    }
+}
+
+/// Parses the `data` of a TXT answer in the JSON format.
+///
+/// Servers either send the character-strings in zone file presentation format, such as
+/// `"she/her" "; preferred"`, or the record as a bare string.
+fn parse_json_txt_data(data: &str) -> io::Result<Vec<u8>> {
+    let data = data.trim();
+    if !data.starts_with('"') {
+        return Ok(data.as_bytes().to_vec());
+    }
+
+    let invalid = || io::Error::new(io::ErrorKind::InvalidData, "malformed TXT data");
+
+    let mut result = Vec::new();
+    let mut bytes = data.bytes();
+
+    while let Some(b) = bytes.next() {
+        match b {
+            b'"' => loop {
+                match bytes.next().ok_or_else(invalid)? {
+                    b'"' => break,
+                    b'\\' => {
+                        let escaped = bytes.next().ok_or_else(invalid)?;
+                        if escaped.is_ascii_digit() {
+                            // \DDD is a decimal byte value
+                            let mut value = u32::from(escaped - b'0');
+                            for _ in 0..2 {
+                                match bytes.next() {
+                                    Some(d) if d.is_ascii_digit() => {
+                                        value = value * 10 + u32::from(d - b'0');
+                                    }
+                                    _ => return Err(invalid()),
+                                }
+                            }
+                            result.push(u8::try_from(value).map_err(|_| invalid())?);
+                        } else {
+                            result.push(escaped);
+                        }
+                    }
+                    other => result.push(other),
+                }
+            },
+            b if b.is_ascii_whitespace() => {}
+            _ => return Err(invalid()),
+        }
+    }
+
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::net::TcpListener;
+    use std::sync::mpsc;
+
+    use super::{DohFormat, DohResolver, parse_json_txt_data};
+    use crate::dns::tests::txt_response;
+    use crate::lookup::TxtResolver;
+
+    struct Request {
+        request_line: String,
+        headers: Vec<String>,
+        body: Vec<u8>,
+    }
+
+    // serves a single canned HTTP response, and sends back the request it received
+    fn serve_once(
+        status: &'static str,
+        content_type: &'static str,
+        body: Vec<u8>,
+    ) -> (String, mpsc::Receiver<Request>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/dns-query", listener.local_addr().unwrap());
+        let (tx, rx) = mpsc::channel();
+
+        std::thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+
+            let mut request_line = String::new();
+            reader.read_line(&mut request_line).unwrap();
+
+            let mut headers = Vec::new();
+            let mut content_length = 0;
+            loop {
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                let line = line.trim_end().to_owned();
+                if line.is_empty() {
+                    break;
+                }
+                if let Some(len) = line.to_lowercase().strip_prefix("content-length:") {
+                    content_length = len.trim().parse().unwrap();
+                }
+                headers.push(line);
+            }
+
+            let mut request_body = vec![0; content_length];
+            reader.read_exact(&mut request_body).unwrap();
+
+            let mut stream = stream;
+            write!(
+                stream,
+                "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                status,
+                content_type,
+                body.len()
+            )
+            .unwrap();
+            stream.write_all(&body).unwrap();
+
+            tx.send(Request {
+                request_line: request_line.trim_end().to_owned(),
+                headers,
+                body: request_body,
+            })
+            .unwrap();
+        });
+
+        (url, rx)
+    }
+
+    #[test]
+    fn test_wire_format() {
+        let mut response = txt_response(&[b"\x12she/her; preferred", b"\x01*"]);
+        response[3] |= 0x20; // AD
+
+        let (url, requests) = serve_once("200 OK", "application/dns-message", response);
+        let lookup = DohResolver::new(url)
+            .lookup_txt("pronouns.example")
+            .unwrap();
+
+        assert_eq!(lookup.records, vec!["she/her; preferred", "*"]);
+        assert!(lookup.authenticated);
+
+        let request = requests.recv().unwrap();
+        assert_eq!(request.request_line, "POST /dns-query HTTP/1.1");
+        assert!(
+            request
+                .headers
+                .iter()
+                .any(|h| h.eq_ignore_ascii_case("content-type: application/dns-message"))
+        );
+
+        assert_eq!(
+            request.body,
+            crate::dns::encode_txt_query("pronouns.example", 0).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_json_format() {
+        let body = br##"{
+            "Status": 0, "TC": false, "AD": false,
+            "Question": [{"name": "pronouns.example.", "type": 16}],
+            "Answer": [
+                {"name": "pronouns.example.", "type": 5, "TTL": 300, "data": "elsewhere.example."},
+                {"name": "pronouns.example.", "type": 16, "TTL": 300, "data": "\"they/them \" \"# \\\"quoted\\\"\""},
+                {"name": "pronouns.example.", "type": 16, "TTL": 300, "data": "she/her"}
+            ]
+        }"##;
+
+        let (url, requests) = serve_once("200 OK", "application/dns-json", body.to_vec());
+        let lookup = DohResolver::new(url)
+            .with_format(DohFormat::Json)
+            .lookup_txt("pronouns.example")
+            .unwrap();
+
+        assert_eq!(lookup.records, vec!["they/them # \"quoted\"", "she/her"]);
+        assert!(!lookup.authenticated);
+        assert_eq!(lookup.ttl, Some(300));
+
+        let request = requests.recv().unwrap();
+        assert_eq!(
+            request.request_line,
+            "GET /dns-query?name=pronouns.example&type=TXT HTTP/1.1"
+        );
+    }
+
+    #[test]
+    fn test_json_name_error() {
+        let (url, _requests) = serve_once(
+            "200 OK",
+            "application/dns-json",
+            br#"{"Status": 3}"#.to_vec(),
+        );
+        let err = DohResolver::new(url)
+            .with_format(DohFormat::Json)
+            .lookup_txt("pronouns.example")
+            .unwrap_err();
+
+        assert_eq!(err.kind(), std::io::ErrorKind::NotFound);
+    }
+
+    #[test]
+    fn test_http_error() {
+        let (url, _requests) = serve_once("500 Internal Server Error", "text/plain", vec![]);
+        let err = DohResolver::new(url)
+            .lookup_txt("pronouns.example")
+            .unwrap_err();
+
+        assert!(err.to_string().contains("500"));
+    }
+
+    #[test]
+    fn test_parse_json_txt_data() {
+        assert_eq!(parse_json_txt_data("she/her").unwrap(), b"she/her");
+        assert_eq!(parse_json_txt_data(r#""she/" "her""#).unwrap(), b"she/her");
+        assert_eq!(
+            parse_json_txt_data(r#""a\\b\"c\033""#).unwrap(),
+            b"a\\b\"c!"
+        );
+        assert!(parse_json_txt_data(r#""unterminated"#).is_err());
+        assert!(parse_json_txt_data(r#""a" b"#).is_err());
+        assert!(parse_json_txt_data(r#""\999""#).is_err());
+    }
+}
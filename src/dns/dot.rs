@@ -0,0 +1,250 @@
+//! DNS-over-TLS (RFC 7858) lookups.
+
+use std::io;
+use std::net::{SocketAddr, TcpStream};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use rustls::pki_types::{CertificateDer, ServerName};
+use rustls::{ClientConfig, ClientConnection, RootCertStore, StreamOwned};
+
+use super::{encode_txt_query, exchange_tcp, lookup_from_response, random_id};
+use crate::lookup::{TxtLookup, TxtResolver};
+
+/// The port DNS-over-TLS servers listen on.
+pub const DOT_PORT: u16 = 853;
+
+type TlsStream = StreamOwned<ClientConnection, TcpStream>;
+
+/// Looks up TXT records with DNS-over-TLS, against a server such as `1.1.1.1:853`
+/// with the name `cloudflare-dns.com`.
+///
+/// The connection is kept open and reused for later lookups, and is re-established
+/// if the server closed it in the meantime.
+///
+/// Whether an answer is `authenticated` is taken from the AD bit set by the server.
+pub struct DotResolver {
+    server: SocketAddr,
+    server_name: ServerName<'static>,
+    tls_config: Arc<ClientConfig>,
+    timeout: Duration,
+    connection: Mutex<Option<TlsStream>>,
+}
+
+impl DotResolver {
+    /// Creates a resolver for `server`, whose certificate must be valid for `server_name`
+    /// and issued by one of the Mozilla root certificates.
+    pub fn new(server: SocketAddr, server_name: &str) -> io::Result<Self> {
+        let roots = RootCertStore {
+            roots: webpki_roots::TLS_SERVER_ROOTS.to_vec(),
+        };
+
+        Ok(DotResolver {
+            server,
+            server_name: ServerName::try_from(server_name.to_owned())
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?,
+            tls_config: tls_config(roots)?,
+            timeout: Duration::from_secs(5),
+            connection: Mutex::new(None),
+        })
+    }
+
+    /// Trusts only the given DER encoded certificates, instead of the Mozilla root certificates.
+    pub fn with_root_certificates(
+        mut self,
+        certificates: impl IntoIterator<Item = Vec<u8>>,
+    ) -> io::Result<Self> {
+        let mut roots = RootCertStore::empty();
+        for certificate in certificates {
+            roots
+                .add(CertificateDer::from(certificate))
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+        }
+
+        self.tls_config = tls_config(roots)?;
+        Ok(self)
+    }
+
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    fn connect(&self) -> io::Result<TlsStream> {
+        let tcp = TcpStream::connect_timeout(&self.server, self.timeout)?;
+        tcp.set_read_timeout(Some(self.timeout))?;
+        tcp.set_write_timeout(Some(self.timeout))?;
+        tcp.set_nodelay(true)?;
+
+        let connection = ClientConnection::new(self.tls_config.clone(), self.server_name.clone())
+            .map_err(io::Error::other)?;
+
+        Ok(StreamOwned::new(connection, tcp))
+    }
+
+    fn exchange(&self, id: u16, query: &[u8]) -> io::Result<Vec<u8>> {
+        let mut connection = self
+            .connection
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+
+        // the server may have closed a connection we kept open, so retry once on a new one
+        if let Some(stream) = connection.as_mut() {
+            match exchange_tcp(stream, id, query) {
+                Ok(response) => return Ok(response),
+                Err(_) => *connection = None,
+            }
+        }
+
+        let stream = connection.insert(self.connect()?);
+        exchange_tcp(stream, id, query).inspect_err(|_| *connection = None)
+    }
+}
+
+impl std::fmt::Debug for DotResolver {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("DotResolver")
+            .field("server", &self.server)
+            .field("server_name", &self.server_name)
+            .field("timeout", &self.timeout)
+            .finish_non_exhaustive()
+    }
+}
+
+impl TxtResolver for DotResolver {
+    fn lookup_txt(&self, name: &str) -> io::Result<TxtLookup> {
+        let id = random_id();
+        let query = encode_txt_query(name, id)?;
+
+        let response = self.exchange(id, &query)?;
+
+        lookup_from_response(name, &response)
+    }
+}
+
+fn tls_config(roots: RootCertStore) -> io::Result<Arc<ClientConfig>> {
+    let config =
+        ClientConfig::builder_with_provider(Arc::new(rustls::crypto::ring::default_provider()))
+            .with_safe_default_protocol_versions()
+            .map_err(io::Error::other)?
+            .with_root_certificates(roots)
+            .with_no_client_auth();
+
+    Ok(Arc::new(config))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Read, Write};
+    use std::net::{SocketAddr, TcpListener};
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use rustls::pki_types::{PrivateKeyDer, PrivatePkcs8KeyDer};
+    use rustls::{ServerConfig, ServerConnection, StreamOwned};
+
+    use super::DotResolver;
+    use crate::dns::tests::txt_response;
+    use crate::lookup::TxtResolver;
+
+    struct StandIn {
+        addr: SocketAddr,
+        certificate: Vec<u8>,
+        connections: Arc<AtomicUsize>,
+    }
+
+    // runs a DNS-over-TLS server with a self-signed certificate for `dns.test`, answering
+    // every query on a connection with `response`, until the client closes it
+    fn serve(response: Vec<u8>) -> StandIn {
+        let certified = rcgen::generate_simple_self_signed(vec!["dns.test".to_string()]).unwrap();
+        let certificate = certified.cert.der().to_vec();
+        let key = PrivateKeyDer::Pkcs8(PrivatePkcs8KeyDer::from(
+            certified.signing_key.serialize_der(),
+        ));
+
+        let config =
+            ServerConfig::builder_with_provider(Arc::new(rustls::crypto::ring::default_provider()))
+                .with_safe_default_protocol_versions()
+                .unwrap()
+                .with_no_client_auth()
+                .with_single_cert(vec![certified.cert.der().clone()], key)
+                .unwrap();
+        let config = Arc::new(config);
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let connections = Arc::new(AtomicUsize::new(0));
+        let accepted = connections.clone();
+
+        std::thread::spawn(move || {
+            for tcp in listener.incoming() {
+                accepted.fetch_add(1, Ordering::SeqCst);
+                let connection = ServerConnection::new(config.clone()).unwrap();
+                let mut stream = StreamOwned::new(connection, tcp.unwrap());
+
+                loop {
+                    let mut len = [0; 2];
+                    if stream.read_exact(&mut len).is_err() {
+                        break;
+                    }
+                    let mut query = vec![0; u16::from_be_bytes(len) as usize];
+                    stream.read_exact(&mut query).unwrap();
+
+                    let mut response = response.clone();
+                    response[..2].copy_from_slice(&query[..2]);
+                    stream
+                        .write_all(&(response.len() as u16).to_be_bytes())
+                        .unwrap();
+                    stream.write_all(&response).unwrap();
+                    stream.flush().unwrap();
+                }
+            }
+        });
+
+        StandIn {
+            addr,
+            certificate,
+            connections,
+        }
+    }
+
+    #[test]
+    fn test_lookup_reuses_connection() {
+        let mut response = txt_response(&[b"\x07she/her"]);
+        response[3] |= 0x20; // AD
+        let stand_in = serve(response);
+
+        let resolver = DotResolver::new(stand_in.addr, "dns.test")
+            .unwrap()
+            .with_root_certificates([stand_in.certificate])
+            .unwrap();
+
+        for _ in 0..3 {
+            let lookup = resolver.lookup_txt("pronouns.example").unwrap();
+            assert_eq!(lookup.records, vec!["she/her"]);
+            assert!(lookup.authenticated);
+        }
+
+        assert_eq!(stand_in.connections.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn test_untrusted_certificate() {
+        let stand_in = serve(txt_response(&[b"\x07she/her"]));
+
+        // the self-signed certificate is not issued by any of the default roots
+        let resolver = DotResolver::new(stand_in.addr, "dns.test").unwrap();
+        assert!(resolver.lookup_txt("pronouns.example").is_err());
+    }
+
+    #[test]
+    fn test_wrong_server_name() {
+        let stand_in = serve(txt_response(&[b"\x07she/her"]));
+
+        let resolver = DotResolver::new(stand_in.addr, "other.test")
+            .unwrap()
+            .with_root_certificates([stand_in.certificate])
+            .unwrap();
+        assert!(resolver.lookup_txt("pronouns.example").is_err());
+    }
+}
@@ -0,0 +1,296 @@
+//! An authoritative DNS server for the records in a [`PronounZone`].
+
+use std::io::{self, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream, UdpSocket};
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+use super::wire::{self, Question, Rcode};
+use crate::zone::PronounZone;
+
+/// Largest response sent over UDP, longer ones are truncated so the client retries over TCP.
+const UDP_MESSAGE_LIMIT: usize = 512;
+
+/// How long a TCP connection may stay idle before it is closed.
+const TCP_TIMEOUT: Duration = Duration::from_secs(10);
+
+const TYPE_ANY: u16 = 255;
+const CLASS_ANY: u16 = 255;
+
+/// Answers TXT queries for `pronouns.<domain>` with the records of each domain in a
+/// [`PronounZone`], over UDP and TCP on the same port.
+///
+/// Queries for other names below the domains are answered with NXDOMAIN, and queries for
+/// names outside of them are refused.
+#[derive(Debug)]
+pub struct DnsServer {
+    udp: UdpSocket,
+    tcp: TcpListener,
+    zone: Arc<RwLock<PronounZone>>,
+}
+
+impl DnsServer {
+    pub fn bind(addr: SocketAddr, zone: PronounZone) -> io::Result<Self> {
+        let udp = UdpSocket::bind(addr)?;
+        // listen on the port that was picked for UDP, in case `addr` has port 0
+        let tcp = TcpListener::bind(udp.local_addr()?)?;
+
+        Ok(DnsServer {
+            udp,
+            tcp,
+            zone: Arc::new(RwLock::new(zone)),
+        })
+    }
+
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.udp.local_addr()
+    }
+
+    /// Serves `zone` from now on, such as after reloading the configuration.
+    pub fn replace_zone(&self, zone: PronounZone) {
+        *self
+            .zone
+            .write()
+            .unwrap_or_else(|poisoned| poisoned.into_inner()) = zone;
+    }
+
+    /// Serves UDP queries on the current thread and TCP connections on background threads.
+    ///
+    /// Only returns if receiving from the UDP socket fails.
+    pub fn run(&self) -> io::Result<()> {
+        let tcp = self.tcp.try_clone()?;
+        let zone = self.zone.clone();
+        std::thread::spawn(move || {
+            for stream in tcp.incoming().flatten() {
+                let zone = zone.clone();
+                std::thread::spawn(move || serve_tcp(&zone, stream));
+            }
+        });
+
+        let mut buf = [0; UDP_MESSAGE_LIMIT];
+        loop {
+            let (n, peer) = match self.udp.recv_from(&mut buf) {
+                Ok(received) => received,
+                // e.g. an ICMP port unreachable from an earlier response on Windows
+                Err(e) if e.kind() == io::ErrorKind::ConnectionReset => continue,
+                Err(e) => return Err(e),
+            };
+
+            let Some(response) = answer(&self.zone, &buf[..n], UDP_MESSAGE_LIMIT) else {
+                continue;
+            };
+
+            // a failed send only affects this client
+            let _ = self.udp.send_to(&response, peer);
+        }
+    }
+}
+
+fn serve_tcp(zone: &RwLock<PronounZone>, mut stream: TcpStream) -> io::Result<()> {
+    stream.set_read_timeout(Some(TCP_TIMEOUT))?;
+    stream.set_write_timeout(Some(TCP_TIMEOUT))?;
+
+    // clients may send several queries on one connection
+    loop {
+        let mut len = [0; 2];
+        match stream.read_exact(&mut len) {
+            Ok(()) => {}
+            Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(()),
+            Err(e) => return Err(e),
+        }
+        let mut query = vec![0; u16::from_be_bytes(len) as usize];
+        stream.read_exact(&mut query)?;
+
+        let Some(response) = answer(zone, &query, u16::MAX as usize) else {
+            return Ok(());
+        };
+
+        let mut message = Vec::with_capacity(response.len() + 2);
+        message.extend_from_slice(&(response.len() as u16).to_be_bytes());
+        message.extend_from_slice(&response);
+        stream.write_all(&message)?;
+    }
+}
+
+/// Builds the response to `query`, or `None` if it should be dropped.
+///
+/// Responses longer than `limit` are sent without answers and with the TC bit set, so the
+/// client retries over TCP.
+fn answer(zone: &RwLock<PronounZone>, query: &[u8], limit: usize) -> Option<Vec<u8>> {
+    // too short to even respond to, or a response itself
+    if query.len() < wire::HEADER_LEN || query[2] & 0x80 != 0 {
+        return None;
+    }
+
+    let id = u16::from_be_bytes([query[0], query[1]]);
+    // the RD bit is copied into the response
+    let flags = u16::from_be_bytes([query[2], query[3]]) & wire::FLAG_RECURSION_DESIRED;
+
+    let Ok(query) = wire::decode_query(query) else {
+        return error_response(id, flags, Rcode::FormatError);
+    };
+
+    if query.opcode() != 0 {
+        return error_response(id, flags, Rcode::NotImplemented);
+    }
+    let [question] = query.questions.as_slice() else {
+        return error_response(id, flags, Rcode::FormatError);
+    };
+
+    let zone = zone.read().unwrap_or_else(|poisoned| poisoned.into_inner());
+
+    if !matches!(question.qclass, wire::CLASS_IN | CLASS_ANY) || !zone.contains_name(&question.name)
+    {
+        return respond(id, flags, Rcode::Refused, question, 0, &[]);
+    }
+
+    let (rcode, answers) = match zone.records_for_name(&question.name) {
+        Some(records) if matches!(question.qtype, wire::TYPE_TXT | TYPE_ANY) => (
+            Rcode::NoError,
+            records
+                .iter()
+                .map(|record| wire::encode_txt_rdata(record))
+                .collect(),
+        ),
+        // the name exists, but has no records of other types
+        Some(_) => (Rcode::NoError, Vec::new()),
+        None if zone.contains_domain(&question.name) => (Rcode::NoError, Vec::new()),
+        None => (Rcode::NameError, Vec::new()),
+    };
+
+    let flags = flags | wire::FLAG_AUTHORITATIVE;
+    let response = respond(id, flags, rcode, question, zone.ttl(), &answers)?;
+
+    if response.len() > limit {
+        return respond(id, flags | wire::FLAG_TRUNCATED, rcode, question, 0, &[]);
+    }
+    Some(response)
+}
+
+fn respond(
+    id: u16,
+    flags: u16,
+    rcode: Rcode,
+    question: &Question,
+    ttl: u32,
+    answers: &[Vec<u8>],
+) -> Option<Vec<u8>> {
+    match wire::encode_response(id, flags, rcode, Some(question), ttl, answers) {
+        Ok(response) => Some(response),
+        // the name in the question can't be encoded back, e.g. because it isn't UTF-8
+        Err(_) => error_response(id, flags, Rcode::FormatError),
+    }
+}
+
+fn error_response(id: u16, flags: u16, rcode: Rcode) -> Option<Vec<u8>> {
+    wire::encode_response(id, flags, rcode, None, 0, &[]).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lookup::TxtResolver;
+    use crate::{LookupOptions, SystemResolver, lookup_pronouns_for_domain};
+
+    const CONFIG: &str = r#"
+        ttl = 300
+
+        [domains]
+        "kinda.red" = ["she/her; preferred", "they/them"]
+    "#;
+
+    fn start(zone: PronounZone) -> (Arc<DnsServer>, SystemResolver) {
+        let server = Arc::new(DnsServer::bind("127.0.0.1:0".parse().unwrap(), zone).unwrap());
+        let resolver = SystemResolver::with_name_servers(vec![server.local_addr().unwrap()])
+            .with_timeout(Duration::from_secs(5));
+
+        let running = server.clone();
+        std::thread::spawn(move || running.run());
+
+        (server, resolver)
+    }
+
+    #[test]
+    fn test_serves_records() {
+        let (_server, resolver) = start(PronounZone::from_toml(CONFIG).unwrap());
+
+        let lookup =
+            lookup_pronouns_for_domain(&resolver, "kinda.red", &LookupOptions::default()).unwrap();
+        assert_eq!(lookup.records.len(), 2);
+        assert!(!lookup.authenticated);
+
+        // names are case-insensitive
+        let lookup = resolver.lookup_txt("PRONOUNS.Kinda.Red").unwrap();
+        assert_eq!(lookup.records, vec!["she/her; preferred", "they/them"]);
+    }
+
+    #[test]
+    fn test_missing_names() {
+        let (_server, resolver) = start(PronounZone::from_toml(CONFIG).unwrap());
+
+        let err = resolver.lookup_txt("pronouns.www.kinda.red").unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::NotFound);
+
+        // no TXT records at the domain itself, but it exists
+        assert_eq!(
+            resolver.lookup_txt("kinda.red").unwrap().records,
+            Vec::<String>::new()
+        );
+
+        let err = resolver.lookup_txt("pronouns.example.org").unwrap_err();
+        assert_eq!(err.to_string(), "refused");
+    }
+
+    #[test]
+    fn test_large_records_use_tcp() {
+        let mut zone = PronounZone::new(60);
+        let records = (0..10)
+            .map(|i| format!("they/them # {}", i.to_string().repeat(100)))
+            .collect::<Vec<String>>();
+        zone.insert("example.org", records.clone()).unwrap();
+
+        let (_server, resolver) = start(zone);
+        let lookup = resolver.lookup_txt("pronouns.example.org").unwrap();
+        assert_eq!(lookup.records, records);
+    }
+
+    #[test]
+    fn test_replace_zone() {
+        let (server, resolver) = start(PronounZone::from_toml(CONFIG).unwrap());
+
+        let mut zone = PronounZone::default();
+        zone.insert("kinda.red", vec!["xe/xem".to_string()])
+            .unwrap();
+        server.replace_zone(zone);
+
+        let lookup = resolver.lookup_txt("pronouns.kinda.red").unwrap();
+        assert_eq!(lookup.records, vec!["xe/xem"]);
+    }
+
+    #[test]
+    fn test_malformed_queries() {
+        let zone = RwLock::new(PronounZone::from_toml(CONFIG).unwrap());
+
+        assert_eq!(answer(&zone, b"\x12\x34", 512), None);
+
+        // a response is never answered
+        let mut query = wire::encode_query("pronouns.kinda.red", 0x1234).unwrap();
+        query[2] |= 0x80;
+        assert_eq!(answer(&zone, &query, 512), None);
+
+        // the question runs past the end of the message
+        let response = answer(
+            &zone,
+            b"\x12\x34\x01\x00\x00\x01\x00\x00\x00\x00\x00\x00\x08pro",
+            512,
+        )
+        .unwrap();
+        assert_eq!(&response[..4], b"\x12\x34\x81\x01");
+
+        // an inverse query
+        let mut query = wire::encode_query("pronouns.kinda.red", 0x1234).unwrap();
+        query[2] |= 0x08;
+        let response = answer(&zone, &query, 512).unwrap();
+        assert_eq!(response[3] & 0x0f, 4);
+    }
+}
@@ -0,0 +1,542 @@
+use std::io;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, TcpStream, UdpSocket};
+use std::time::{Duration, Instant};
+
+use super::{
+    check_question, exchange_tcp, lookup_from_response, random_id, txt_question, txt_records, wire,
+};
+use crate::lookup::{TxtLookup, TxtResolver};
+
+const DNS_PORT: u16 = 53;
+
+/// Largest response accepted over UDP, anything longer has to be retried over TCP.
+const UDP_MESSAGE_LIMIT: usize = 512;
+
+/// A stub resolver that sends queries to the name servers configured on the system,
+/// read from `/etc/resolv.conf` on Unix.
+///
+/// Queries are sent over UDP, and retried over TCP if the response was truncated.
+///
+/// Whether an answer is `authenticated` is taken from the AD bit set by those name
+/// servers, so it is only as trustworthy as the name servers and the network path to them.
+#[derive(Debug, Clone)]
+pub struct SystemResolver {
+    name_servers: Vec<SocketAddr>,
+    timeout: Duration,
+    attempts: u32,
+}
+
+impl SystemResolver {
+    pub fn new() -> io::Result<Self> {
+        #[cfg(windows)]
+        {
+            Ok(SystemResolver::with_name_servers(
+                windows::default_name_servers()?,
+            ))
+        }
+        #[cfg(not(windows))]
+        {
+            let contents = std::fs::read_to_string("/etc/resolv.conf").or_else(|e| {
+                // without a resolv.conf, the local name server is used
+                match e.kind() {
+                    io::ErrorKind::NotFound => Ok(String::new()),
+                    _ => Err(e),
+                }
+            })?;
+
+            Ok(parse_resolv_conf(&contents))
+        }
+    }
+
+    pub fn with_name_servers(name_servers: Vec<SocketAddr>) -> Self {
+        SystemResolver {
+            name_servers,
+            timeout: Duration::from_secs(5),
+            attempts: 2,
+        }
+    }
+
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    pub fn with_attempts(mut self, attempts: u32) -> Self {
+        self.attempts = attempts.max(1);
+        self
+    }
+
+    pub fn name_servers(&self) -> &[SocketAddr] {
+        &self.name_servers
+    }
+
+    fn exchange(&self, query: &[u8], id: u16) -> io::Result<Vec<u8>> {
+        if self.name_servers.is_empty() {
+            return Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                "No DNS servers configured",
+            ));
+        }
+
+        let mut last_err = None;
+
+        for attempt in 0..self.attempts as usize * self.name_servers.len() {
+            let server = self.name_servers[attempt % self.name_servers.len()];
+
+            let result = match exchange_udp(server, id, query, self.timeout) {
+                Ok(response) if wire::is_truncated(&response) => {
+                    exchange_tcp_with(server, id, query, self.timeout)
+                }
+                result => result,
+            };
+
+            match result {
+                Ok(response) => return Ok(response),
+                // try the next server on any error, like a timeout or a refused connection,
+                // over UDP or TCP
+                Err(e) => last_err = Some(e),
+            }
+        }
+
+        Err(last_err.unwrap_or_else(|| io::Error::from(io::ErrorKind::TimedOut)))
+    }
+}
+
+impl TxtResolver for SystemResolver {
+    fn lookup_txt(&self, name: &str) -> io::Result<TxtLookup> {
+        let id = random_id();
+        let response = self.exchange(&wire::encode_query(name, id)?, id)?;
+
+        lookup_from_response(name, &response)
+    }
+}
+
+fn exchange_udp(
+    server: SocketAddr,
+    id: u16,
+    query: &[u8],
+    timeout: Duration,
+) -> io::Result<Vec<u8>> {
+    let bind_addr: SocketAddr = match server {
+        SocketAddr::V4(_) => (Ipv4Addr::UNSPECIFIED, 0).into(),
+        SocketAddr::V6(_) => (Ipv6Addr::UNSPECIFIED, 0).into(),
+    };
+
+    let socket = UdpSocket::bind(bind_addr)?;
+    socket.connect(server)?;
+    socket.send(query)?;
+
+    let deadline = Instant::now() + timeout;
+    let mut buf = [0; UDP_MESSAGE_LIMIT];
+
+    loop {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() {
+            return Err(io::Error::from(io::ErrorKind::TimedOut));
+        }
+        socket.set_read_timeout(Some(remaining))?;
+
+        let n = socket.recv(&mut buf)?;
+        let response = &buf[..n];
+
+        // ignore anything that isn't a response to our query
+        if n >= wire::HEADER_LEN && response[..2] == id.to_be_bytes() && response[2] & 0x80 != 0 {
+            return Ok(response.to_vec());
+        }
+    }
+}
+
+fn exchange_tcp_with(
+    server: SocketAddr,
+    id: u16,
+    query: &[u8],
+    timeout: Duration,
+) -> io::Result<Vec<u8>> {
+    let mut stream = TcpStream::connect_timeout(&server, timeout)?;
+    stream.set_read_timeout(Some(timeout))?;
+    stream.set_write_timeout(Some(timeout))?;
+
+    exchange_tcp(&mut stream, id, query)
+}
+
+/// Reads the name servers and options from the contents of a `resolv.conf` file.
+///
+/// Like glibc, only the first three name servers are used, and the name server on
+/// the local machine is used when none are listed.
+fn parse_resolv_conf(contents: &str) -> SystemResolver {
+    const MAX_NAME_SERVERS: usize = 3;
+
+    let mut resolver = SystemResolver::with_name_servers(Vec::new());
+
+    for line in contents.lines() {
+        let line = line.split(['#', ';']).next().unwrap_or_default();
+        let mut words = line.split_whitespace();
+
+        match words.next() {
+            Some("nameserver") => {
+                let Some(addr) = words.next().and_then(parse_name_server) else {
+                    continue;
+                };
+                if resolver.name_servers.len() < MAX_NAME_SERVERS {
+                    resolver.name_servers.push(addr);
+                }
+            }
+            Some("options") => {
+                for option in words {
+                    match option.split_once(':') {
+                        Some(("timeout", secs)) => {
+                            if let Ok(secs) = secs.parse::<u64>() {
+                                resolver.timeout = Duration::from_secs(secs.clamp(1, 30));
+                            }
+                        }
+                        Some(("attempts", attempts)) => {
+                            if let Ok(attempts) = attempts.parse::<u32>() {
+                                resolver.attempts = attempts.clamp(1, 5);
+                            }
+                        }
+                        _ => {}
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    if resolver.name_servers.is_empty() {
+        resolver.name_servers = vec![
+            (Ipv4Addr::LOCALHOST, DNS_PORT).into(),
+            (Ipv6Addr::LOCALHOST, DNS_PORT).into(),
+        ];
+    }
+
+    resolver
+}
+
+fn parse_name_server(addr: &str) -> Option<SocketAddr> {
+    // IPv6 addresses may have a zone, which has to be an interface index for us to use it
+    let (ip, zone) = match addr.split_once('%') {
+        Some((ip, zone)) => (ip, Some(zone.parse::<u32>().ok()?)),
+        None => (addr, None),
+    };
+
+    match ip.parse::<IpAddr>().ok()? {
+        IpAddr::V4(ip) if zone.is_none() => Some((ip, DNS_PORT).into()),
+        IpAddr::V4(_) => None,
+        IpAddr::V6(ip) => Some(SocketAddr::V6(std::net::SocketAddrV6::new(
+            ip,
+            DNS_PORT,
+            0,
+            zone.unwrap_or(0),
+        ))),
+    }
+}
+
+/// Queries the TXT records of `domain`, returning each record with its
+/// character-strings joined together.
+pub fn query_txt_raw(domain: &str) -> io::Result<Vec<Vec<u8>>> {
+    let resolver = SystemResolver::new()?;
+    let id = random_id();
+    let response =
+        wire::decode_response(&resolver.exchange(&wire::encode_query(domain, id)?, id)?)?;
+    check_question(domain, &txt_question(&response)?)?;
+
+    txt_records(&response)
+}
+
+/// Queries the TXT records of `domain`.
+///
+/// Fails with [`io::ErrorKind::InvalidData`] if any of the records is not valid UTF-8,
+/// use [`query_txt_raw`] to get at the bytes of such records.
+pub fn query_txt(domain: &str) -> io::Result<Vec<String>> {
+    let lookup = SystemResolver::new()?.lookup_txt(domain)?;
+
+    match lookup.invalid.first() {
+        Some(invalid) => Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("{}: {}", domain, invalid),
+        )),
+        None => Ok(lookup.records),
+    }
+}
+
+#[cfg(windows)]
+mod windows {
+    use std::io;
+
+    pub(super) fn default_name_servers() -> io::Result<Vec<std::net::SocketAddr>> {
+        use windows::Win32::Foundation::ERROR_BUFFER_OVERFLOW;
+        use windows::Win32::NetworkManagement::IpHelper::GetNetworkParams;
+
+        // First call to get the required buffer size
+        let mut buf_len: u32 = 0;
+        let result = unsafe { GetNetworkParams(None, &mut buf_len) };
+
+        if result != ERROR_BUFFER_OVERFLOW {
+            return Err(io::Error::other(format!(
+                "GetNetworkParams failed with error {}",
+                result.0
+            )));
+        }
+
+        // Allocate buffer and call again
+        let mut buffer = vec![0u8; buf_len as usize];
+        let fixed_info = buffer.as_mut_ptr()
+            as *mut windows::Win32::NetworkManagement::IpHelper::FIXED_INFO_W2KSP1;
+
+        let result = unsafe { GetNetworkParams(Some(fixed_info), &mut buf_len) };
+
+        if result.0 != 0 {
+            return Err(io::Error::other(format!(
+                "GetNetworkParams failed with error {}",
+                result.0
+            )));
+        }
+
+        // Extract DNS servers
+        let mut nameservers: Vec<std::net::IpAddr> = Vec::new();
+        unsafe {
+            let fixed = &*fixed_info;
+
+            // Add primary DNS server
+            let dns_addr = std::ffi::CStr::from_ptr(
+                fixed.DnsServerList.IpAddress.String.as_ptr() as *const i8
+            )
+            .to_string_lossy();
+
+            if !dns_addr.is_empty() && dns_addr != "0.0.0.0" {
+                if let Ok(addr) = dns_addr.parse::<std::net::IpAddr>() {
+                    nameservers.push(addr);
+                }
+            }
+
+            // Add additional DNS servers from linked list
+            let mut current = fixed.DnsServerList.Next;
+            while !current.is_null() {
+                let dns_addr =
+                    std::ffi::CStr::from_ptr((*current).IpAddress.String.as_ptr() as *const i8)
+                        .to_string_lossy();
+
+                if !dns_addr.is_empty() && dns_addr != "0.0.0.0" {
+                    if let Ok(addr) = dns_addr.parse::<std::net::IpAddr>() {
+                        nameservers.push(addr);
+                    }
+                }
+                current = (*current).Next;
+            }
+        }
+
+        if nameservers.is_empty() {
+            return Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                "No DNS servers found",
+            ));
+        }
+
+        // convert IpAddr to SocketAddr with default port 53
+        let nameservers: Vec<std::net::SocketAddr> = nameservers
+            .into_iter()
+            .map(|ip| std::net::SocketAddr::new(ip, super::DNS_PORT))
+            .collect();
+
+        Ok(nameservers)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Read, Write};
+    use std::net::{SocketAddr, TcpListener, UdpSocket};
+    use std::time::Duration;
+
+    use super::{SystemResolver, parse_resolv_conf};
+    use crate::dns::tests::{KINDA_RED_RESPONSE, txt_response};
+    use crate::lookup::TxtResolver;
+
+    // answers a single query on a local socket with `response`, using the id of the query
+    fn serve_once(response: Vec<u8>) -> SystemResolver {
+        let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let addr = socket.local_addr().unwrap();
+
+        std::thread::spawn(move || {
+            let mut buf = [0; 512];
+            let (_, peer) = socket.recv_from(&mut buf).unwrap();
+            let mut response = response;
+            response[..2].copy_from_slice(&buf[..2]);
+            socket.send_to(&response, peer).unwrap();
+        });
+
+        SystemResolver::with_name_servers(vec![addr]).with_timeout(Duration::from_secs(5))
+    }
+
+    #[test]
+    fn test_authenticated_data() {
+        let mut response = txt_response(&[b"\x07she/her"]);
+        response[3] |= 0x20; // AD
+
+        let lookup = serve_once(response).lookup_txt("pronouns.example").unwrap();
+        assert_eq!(lookup.records, vec!["she/her".to_string()]);
+        assert!(lookup.authenticated);
+
+        let lookup = serve_once(txt_response(&[b"\x07she/her"]))
+            .lookup_txt("pronouns.example")
+            .unwrap();
+        assert!(!lookup.authenticated);
+    }
+
+    #[test]
+    fn test_tcp_fallback() {
+        // a 600 byte record does not fit in a UDP response
+        let record = "a".repeat(600);
+        let mut rdata = Vec::new();
+        for chunk in record.as_bytes().chunks(255) {
+            rdata.push(chunk.len() as u8);
+            rdata.extend_from_slice(chunk);
+        }
+        let response = txt_response(&[&rdata]);
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let socket = UdpSocket::bind(addr).unwrap();
+
+        std::thread::spawn(move || {
+            // over UDP, only the header and question with the TC bit set
+            let mut buf = [0; 512];
+            let (_, peer) = socket.recv_from(&mut buf).unwrap();
+            let mut truncated = txt_response(&[]);
+            truncated[..2].copy_from_slice(&buf[..2]);
+            truncated[2] |= 0x02;
+            socket.send_to(&truncated, peer).unwrap();
+
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut len = [0; 2];
+            stream.read_exact(&mut len).unwrap();
+            let mut query = vec![0; u16::from_be_bytes(len) as usize];
+            stream.read_exact(&mut query).unwrap();
+
+            let mut response = response;
+            response[..2].copy_from_slice(&query[..2]);
+            stream
+                .write_all(&(response.len() as u16).to_be_bytes())
+                .unwrap();
+            stream.write_all(&response).unwrap();
+        });
+
+        let lookup = SystemResolver::with_name_servers(vec![addr])
+            .lookup_txt("pronouns.example")
+            .unwrap();
+        assert_eq!(lookup.records, vec![record]);
+    }
+
+    #[test]
+    fn test_next_server_on_error() {
+        // nothing listens on the first server, so it refuses the query
+        let refused = UdpSocket::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap();
+        let addr = serve_once(txt_response(&[b"\x07she/her"])).name_servers()[0];
+
+        let lookup = SystemResolver::with_name_servers(vec![refused, addr])
+            .with_timeout(Duration::from_secs(5))
+            .lookup_txt("pronouns.example")
+            .unwrap();
+        assert_eq!(lookup.records, vec!["she/her"]);
+
+        // the first server truncates its response, but doesn't answer over TCP
+        let truncating = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let truncating_addr = truncating.local_addr().unwrap();
+        std::thread::spawn(move || {
+            let mut buf = [0; 512];
+            let (_, peer) = truncating.recv_from(&mut buf).unwrap();
+            let mut truncated = txt_response(&[]);
+            truncated[..2].copy_from_slice(&buf[..2]);
+            truncated[2] |= 0x02;
+            truncating.send_to(&truncated, peer).unwrap();
+        });
+        let addr = serve_once(txt_response(&[b"\x07she/her"])).name_servers()[0];
+
+        let lookup = SystemResolver::with_name_servers(vec![truncating_addr, addr])
+            .with_timeout(Duration::from_secs(5))
+            .lookup_txt("pronouns.example")
+            .unwrap();
+        assert_eq!(lookup.records, vec!["she/her"]);
+    }
+
+    #[test]
+    fn test_ignores_mismatched_id() {
+        let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let addr = socket.local_addr().unwrap();
+
+        std::thread::spawn(move || {
+            let mut buf = [0; 512];
+            let (_, peer) = socket.recv_from(&mut buf).unwrap();
+
+            // a spoofed response with the wrong id, followed by the real one
+            let mut response = txt_response(&[b"\x07he/him"]);
+            response[..2].copy_from_slice(&(!u16::from_be_bytes([buf[0], buf[1]])).to_be_bytes());
+            socket.send_to(&response, peer).unwrap();
+
+            let mut response = txt_response(&[b"\x07she/her"]);
+            response[..2].copy_from_slice(&buf[..2]);
+            socket.send_to(&response, peer).unwrap();
+        });
+
+        let lookup = SystemResolver::with_name_servers(vec![addr])
+            .lookup_txt("pronouns.example")
+            .unwrap();
+        assert_eq!(lookup.records, vec!["she/her"]);
+    }
+
+    #[test]
+    fn test_parse_resolv_conf() {
+        let resolver = parse_resolv_conf(
+            "# generated by NetworkManager\n\
+             search example.com\n\
+             nameserver 192.0.2.1\n\
+             nameserver 2001:db8::1 ; secondary\n\
+             nameserver fe80::1%2\n\
+             nameserver 192.0.2.4\n\
+             options edns0 timeout:2 attempts:3\n",
+        );
+
+        assert_eq!(
+            resolver.name_servers(),
+            [
+                "192.0.2.1:53".parse::<SocketAddr>().unwrap(),
+                "[2001:db8::1]:53".parse().unwrap(),
+                "[fe80::1%2]:53".parse().unwrap(),
+            ]
+        );
+        assert_eq!(resolver.timeout, Duration::from_secs(2));
+        assert_eq!(resolver.attempts, 3);
+    }
+
+    #[test]
+    fn test_parse_resolv_conf_defaults() {
+        let resolver = parse_resolv_conf("nameserver fe80::1%eth0\nnameserver not-an-ip\n");
+
+        assert_eq!(
+            resolver.name_servers(),
+            [
+                "127.0.0.1:53".parse::<SocketAddr>().unwrap(),
+                "[::1]:53".parse().unwrap(),
+            ]
+        );
+        assert_eq!(resolver.timeout, Duration::from_secs(5));
+        assert_eq!(resolver.attempts, 2);
+    }
+
+    #[test]
+    fn test_query_txt() {
+        let resolver = serve_once(KINDA_RED_RESPONSE.to_vec());
+        let results = resolver
+            .lookup_txt("pronouns.kinda.red")
+            .expect("Failed to query TXT records")
+            .records;
+        assert!(
+            results.contains(&"she/her; preferred".to_string()),
+            "Expected pronoun record not found, got: {:?}",
+            results
+        );
+    }
+}
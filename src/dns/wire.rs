@@ -0,0 +1,498 @@
+//! The parts of the DNS wire format (RFC 1035 section 4) needed to look up TXT records.
+
+use std::fmt::Display;
+use std::io;
+
+pub(crate) const HEADER_LEN: usize = 12;
+
+pub(crate) const TYPE_TXT: u16 = 16;
+pub(crate) const CLASS_IN: u16 = 1;
+
+pub(crate) const FLAG_RESPONSE: u16 = 0x8000;
+#[cfg(feature = "dns_server")]
+pub(crate) const FLAG_AUTHORITATIVE: u16 = 0x0400;
+pub(crate) const FLAG_TRUNCATED: u16 = 0x0200;
+pub(crate) const FLAG_RECURSION_DESIRED: u16 = 0x0100;
+/// The AD (authentic data) bit.
+///
+/// Setting it in a query asks the resolver to report whether the answer was
+/// validated with DNSSEC, see RFC 6840 section 5.7.
+const FLAG_AUTHENTIC_DATA: u16 = 0x0020;
+
+const MAX_NAME_LEN: usize = 255;
+const MAX_LABEL_LEN: usize = 63;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Rcode {
+    NoError,
+    FormatError,
+    ServerFailure,
+    NameError,
+    NotImplemented,
+    Refused,
+    Other(u8),
+}
+
+impl Rcode {
+    pub(crate) fn from_u8(rcode: u8) -> Self {
+        match rcode {
+            0 => Rcode::NoError,
+            1 => Rcode::FormatError,
+            2 => Rcode::ServerFailure,
+            3 => Rcode::NameError,
+            4 => Rcode::NotImplemented,
+            5 => Rcode::Refused,
+            n => Rcode::Other(n),
+        }
+    }
+
+    #[cfg(feature = "dns_server")]
+    pub(crate) fn to_u8(self) -> u8 {
+        match self {
+            Rcode::NoError => 0,
+            Rcode::FormatError => 1,
+            Rcode::ServerFailure => 2,
+            Rcode::NameError => 3,
+            Rcode::NotImplemented => 4,
+            Rcode::Refused => 5,
+            Rcode::Other(n) => n & 0x0f,
+        }
+    }
+}
+
+impl Display for Rcode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Rcode::NoError => write!(f, "no error"),
+            Rcode::FormatError => write!(f, "format error"),
+            Rcode::ServerFailure => write!(f, "server failure"),
+            Rcode::NameError => write!(f, "no such name"),
+            Rcode::NotImplemented => write!(f, "not implemented"),
+            Rcode::Refused => write!(f, "refused"),
+            Rcode::Other(n) => write!(f, "response code {}", n),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Question {
+    pub(crate) name: String,
+    pub(crate) qtype: u16,
+    pub(crate) qclass: u16,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Answer {
+    pub(crate) name: String,
+    pub(crate) rtype: u16,
+    pub(crate) rclass: u16,
+    pub(crate) ttl: u32,
+    pub(crate) rdata: Vec<u8>,
+}
+
+/// A decoded response, without its authority and additional sections.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Response {
+    pub(crate) id: u16,
+    pub(crate) truncated: bool,
+    pub(crate) authenticated: bool,
+    pub(crate) rcode: Rcode,
+    pub(crate) questions: Vec<Question>,
+    pub(crate) answers: Vec<Answer>,
+}
+
+fn invalid(msg: impl Display) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg.to_string())
+}
+
+/// Encodes a recursive query for the TXT records of `name`, asking for the AD bit in the response.
+pub(crate) fn encode_query(name: &str, id: u16) -> io::Result<Vec<u8>> {
+    let mut msg = Vec::with_capacity(HEADER_LEN + name.len() + 6);
+    msg.extend_from_slice(&id.to_be_bytes());
+    msg.extend_from_slice(&(FLAG_RECURSION_DESIRED | FLAG_AUTHENTIC_DATA).to_be_bytes());
+    // one question, no other records
+    msg.extend_from_slice(&[0, 1, 0, 0, 0, 0, 0, 0]);
+
+    write_name(&mut msg, name)?;
+    msg.extend_from_slice(&TYPE_TXT.to_be_bytes());
+    msg.extend_from_slice(&CLASS_IN.to_be_bytes());
+
+    Ok(msg)
+}
+
+/// Writes `name` as a sequence of labels, without compression.
+pub(crate) fn write_name(msg: &mut Vec<u8>, name: &str) -> io::Result<()> {
+    let name = name.strip_suffix('.').unwrap_or(name);
+    let start = msg.len();
+
+    if !name.is_empty() {
+        for label in name.split('.') {
+            if label.is_empty() || label.len() > MAX_LABEL_LEN {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("invalid label in domain name {:?}", name),
+                ));
+            }
+
+            msg.push(label.len() as u8);
+            msg.extend_from_slice(label.as_bytes());
+        }
+    }
+    msg.push(0);
+
+    if msg.len() - start > MAX_NAME_LEN {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("domain name {:?} is too long", name),
+        ));
+    }
+
+    Ok(())
+}
+
+struct Reader<'a> {
+    msg: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, n: usize) -> io::Result<&'a [u8]> {
+        let bytes = self
+            .msg
+            .get(self.pos..self.pos + n)
+            .ok_or_else(|| invalid("message is too short"))?;
+        self.pos += n;
+        Ok(bytes)
+    }
+
+    fn u16(&mut self) -> io::Result<u16> {
+        let bytes = self.take(2)?;
+        Ok(u16::from_be_bytes([bytes[0], bytes[1]]))
+    }
+
+    fn u32(&mut self) -> io::Result<u32> {
+        let bytes = self.take(4)?;
+        Ok(u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+
+    /// Reads a possibly compressed name, in presentation format without the trailing dot.
+    fn name(&mut self) -> io::Result<String> {
+        let mut labels: Vec<String> = Vec::new();
+        let mut len = 0;
+        let mut pos = self.pos;
+        // where reading continues after the name, once the first pointer has been followed
+        let mut end = None;
+
+        loop {
+            let label_len = *self
+                .msg
+                .get(pos)
+                .ok_or_else(|| invalid("message is too short"))?
+                as usize;
+
+            match label_len & 0xc0 {
+                0x00 if label_len == 0 => {
+                    pos += 1;
+                    break;
+                }
+                0x00 => {
+                    let label = self
+                        .msg
+                        .get(pos + 1..pos + 1 + label_len)
+                        .ok_or_else(|| invalid("message is too short"))?;
+
+                    len += label_len + 1;
+                    if len > MAX_NAME_LEN {
+                        return Err(invalid("name is too long"));
+                    }
+
+                    labels.push(String::from_utf8_lossy(label).into_owned());
+                    pos += 1 + label_len;
+                }
+                0xc0 => {
+                    let low = *self
+                        .msg
+                        .get(pos + 1)
+                        .ok_or_else(|| invalid("message is too short"))?;
+                    let target = ((label_len & 0x3f) << 8) | low as usize;
+
+                    // only pointing backwards guarantees that we don't loop forever
+                    if target >= pos {
+                        return Err(invalid("name compression pointer does not point backwards"));
+                    }
+
+                    end.get_or_insert(pos + 2);
+                    pos = target;
+                }
+                _ => return Err(invalid("unsupported label type")),
+            }
+        }
+
+        self.pos = end.unwrap_or(pos);
+        Ok(labels.join("."))
+    }
+}
+
+/// Whether the TC bit is set in the header of `msg`, meaning the response did not fit.
+#[cfg(feature = "dns_resolve")]
+pub(crate) fn is_truncated(msg: &[u8]) -> bool {
+    msg.len() >= HEADER_LEN && u16::from_be_bytes([msg[2], msg[3]]) & FLAG_TRUNCATED != 0
+}
+
+/// Decodes the header, question and answer sections of a response.
+pub(crate) fn decode_response(msg: &[u8]) -> io::Result<Response> {
+    let mut reader = Reader { msg, pos: 0 };
+
+    let id = reader.u16()?;
+    let flags = reader.u16()?;
+    let qdcount = reader.u16()?;
+    let ancount = reader.u16()?;
+    // the authority and additional sections are not needed
+    reader.take(4)?;
+
+    if flags & FLAG_RESPONSE == 0 {
+        return Err(invalid("message is not a response"));
+    }
+
+    let mut questions = Vec::new();
+    for _ in 0..qdcount {
+        questions.push(Question {
+            name: reader.name()?,
+            qtype: reader.u16()?,
+            qclass: reader.u16()?,
+        });
+    }
+
+    let mut answers = Vec::new();
+    for _ in 0..ancount {
+        let name = reader.name()?;
+        let rtype = reader.u16()?;
+        let rclass = reader.u16()?;
+        let ttl = reader.u32()?;
+        let rdlength = reader.u16()? as usize;
+        let rdata = reader.take(rdlength)?.to_vec();
+
+        answers.push(Answer {
+            name,
+            rtype,
+            rclass,
+            ttl,
+            rdata,
+        });
+    }
+
+    Ok(Response {
+        id,
+        truncated: flags & FLAG_TRUNCATED != 0,
+        authenticated: flags & FLAG_AUTHENTIC_DATA != 0,
+        rcode: Rcode::from_u8((flags & 0x000f) as u8),
+        questions,
+        answers,
+    })
+}
+
+/// A decoded query, of which only the header and question section are looked at.
+#[cfg(feature = "dns_server")]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Query {
+    pub(crate) id: u16,
+    pub(crate) flags: u16,
+    pub(crate) questions: Vec<Question>,
+}
+
+#[cfg(feature = "dns_server")]
+impl Query {
+    pub(crate) fn opcode(&self) -> u8 {
+        ((self.flags >> 11) & 0x0f) as u8
+    }
+}
+
+/// Decodes the header and question section of a query.
+#[cfg(feature = "dns_server")]
+pub(crate) fn decode_query(msg: &[u8]) -> io::Result<Query> {
+    let mut reader = Reader { msg, pos: 0 };
+
+    let id = reader.u16()?;
+    let flags = reader.u16()?;
+    let qdcount = reader.u16()?;
+    reader.take(6)?;
+
+    if flags & FLAG_RESPONSE != 0 {
+        return Err(invalid("message is not a query"));
+    }
+
+    let mut questions = Vec::new();
+    for _ in 0..qdcount {
+        questions.push(Question {
+            name: reader.name()?,
+            qtype: reader.u16()?,
+            qclass: reader.u16()?,
+        });
+    }
+
+    Ok(Query {
+        id,
+        flags,
+        questions,
+    })
+}
+
+/// Encodes a response with the given header flags, and TXT answers for the name in the question.
+#[cfg(feature = "dns_server")]
+pub(crate) fn encode_response(
+    id: u16,
+    flags: u16,
+    rcode: Rcode,
+    question: Option<&Question>,
+    ttl: u32,
+    answers: &[Vec<u8>],
+) -> io::Result<Vec<u8>> {
+    let mut msg = Vec::with_capacity(512);
+    msg.extend_from_slice(&id.to_be_bytes());
+    msg.extend_from_slice(&(flags | FLAG_RESPONSE | u16::from(rcode.to_u8())).to_be_bytes());
+    msg.extend_from_slice(&u16::from(question.is_some()).to_be_bytes());
+    msg.extend_from_slice(&(answers.len() as u16).to_be_bytes());
+    msg.extend_from_slice(&[0, 0, 0, 0]);
+
+    if let Some(question) = question {
+        write_name(&mut msg, &question.name)?;
+        msg.extend_from_slice(&question.qtype.to_be_bytes());
+        msg.extend_from_slice(&question.qclass.to_be_bytes());
+    }
+
+    for rdata in answers {
+        let rdlength = u16::try_from(rdata.len())
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "record is too long"))?;
+
+        // pointer to the name in the question
+        msg.extend_from_slice(&[0xc0, HEADER_LEN as u8]);
+        msg.extend_from_slice(&TYPE_TXT.to_be_bytes());
+        msg.extend_from_slice(&CLASS_IN.to_be_bytes());
+        msg.extend_from_slice(&ttl.to_be_bytes());
+        msg.extend_from_slice(&rdlength.to_be_bytes());
+        msg.extend_from_slice(rdata);
+    }
+
+    Ok(msg)
+}
+
+/// Encodes `record` as TXT RDATA, split into character-strings of at most 255 bytes.
+#[cfg(feature = "dns_server")]
+pub(crate) fn encode_txt_rdata(record: &str) -> Vec<u8> {
+    let mut rdata = Vec::with_capacity(record.len() + record.len() / 255 + 1);
+
+    // an empty record still has one empty character-string
+    if record.is_empty() {
+        rdata.push(0);
+    }
+
+    for chunk in record.as_bytes().chunks(255) {
+        rdata.push(chunk.len() as u8);
+        rdata.extend_from_slice(chunk);
+    }
+
+    rdata
+}
+
+/// Splits TXT RDATA into its character-strings.
+pub(crate) fn decode_txt_rdata(rdata: &[u8]) -> io::Result<Vec<&[u8]>> {
+    let mut chunks = Vec::new();
+    let mut reader = Reader { msg: rdata, pos: 0 };
+
+    while reader.pos < rdata.len() {
+        let len = reader.take(1)?[0] as usize;
+        chunks.push(
+            reader
+                .take(len)
+                .map_err(|_| invalid("TXT character-string is longer than the record"))?,
+        );
+    }
+
+    Ok(chunks)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encode_query() {
+        let query = encode_query("pronouns.example.", 0xabcd).unwrap();
+        assert_eq!(
+            query,
+            b"\xab\xcd\x01\x20\x00\x01\x00\x00\x00\x00\x00\x00\
+              \x08pronouns\x07example\x00\x00\x10\x00\x01"
+        );
+    }
+
+    #[test]
+    fn test_encode_invalid_names() {
+        assert!(encode_query("pronouns..example", 0).is_err());
+        assert!(encode_query(&format!("{}.example", "a".repeat(64)), 0).is_err());
+        assert!(encode_query(&["abcdefgh"; 32].join("."), 0).is_err());
+    }
+
+    #[test]
+    fn test_compression_loop() {
+        // an answer whose name points at itself
+        let mut msg = b"\x00\x00\x81\x80\x00\x00\x00\x01\x00\x00\x00\x00".to_vec();
+        msg.extend_from_slice(b"\xc0\x0c\x00\x10\x00\x01\x00\x00\x00\x00\x00\x00");
+        assert!(decode_response(&msg).is_err());
+    }
+
+    #[cfg(feature = "dns_server")]
+    #[test]
+    fn test_query_round_trip() {
+        let query = decode_query(&encode_query("Pronouns.Example", 0xabcd).unwrap()).unwrap();
+        assert_eq!(query.id, 0xabcd);
+        assert_eq!(query.opcode(), 0);
+        assert_eq!(
+            query.questions,
+            vec![Question {
+                name: "Pronouns.Example".to_string(),
+                qtype: TYPE_TXT,
+                qclass: CLASS_IN,
+            }]
+        );
+
+        let record = "a".repeat(300);
+        let response = encode_response(
+            query.id,
+            FLAG_AUTHORITATIVE,
+            Rcode::NoError,
+            query.questions.first(),
+            3600,
+            &[encode_txt_rdata("she/her"), encode_txt_rdata(&record)],
+        )
+        .unwrap();
+
+        let response = decode_response(&response).unwrap();
+        assert_eq!(response.id, 0xabcd);
+        assert_eq!(response.rcode, Rcode::NoError);
+        assert_eq!(response.answers[0].name, "Pronouns.Example");
+        assert_eq!(response.answers[0].ttl, 3600);
+        assert_eq!(
+            decode_txt_rdata(&response.answers[1].rdata).unwrap(),
+            vec![&record.as_bytes()[..255], &record.as_bytes()[255..]]
+        );
+    }
+
+    #[cfg(feature = "dns_server")]
+    #[test]
+    fn test_encode_txt_rdata() {
+        assert_eq!(encode_txt_rdata(""), b"\x00");
+        assert_eq!(encode_txt_rdata("he/him"), b"\x06he/him");
+        assert_eq!(encode_txt_rdata(&"a".repeat(255)).len(), 256);
+        assert_eq!(encode_txt_rdata(&"a".repeat(256)).len(), 258);
+    }
+
+    #[test]
+    fn test_decode_txt_rdata() {
+        assert_eq!(
+            decode_txt_rdata(b"\x04she/\x03her\x00").unwrap(),
+            vec![&b"she/"[..], &b"her"[..], &b""[..]]
+        );
+        assert!(decode_txt_rdata(b"\x04she").is_err());
+        assert!(decode_txt_rdata(b"").unwrap().is_empty());
+    }
+}
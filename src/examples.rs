@@ -0,0 +1,123 @@
+//! Example sentences that show how to refer to someone with their pronouns.
+//!
+//! ```
+//! use podns::examples::sentences;
+//!
+//! let record = podns::parse_record("ze/hir/hir/hirs/hirself").unwrap();
+//! assert_eq!(
+//!     sentences(&record.set.unwrap()),
+//!     vec![
+//!         "Ze went to the park.",
+//!         "I went with hir.",
+//!         "Hir bike is red.",
+//!         "The bike is hirs.",
+//!         "Ze rode the bike hirself.",
+//!     ]
+//! );
+//! ```
+
+use crate::pronouns::{PronounDef, PronounSet};
+
+/// A sentence per form of `set`, or what `*` and `!` mean.
+///
+/// The sentences are English, so there are none for sets in other languages.
+pub fn sentences(set: &PronounSet) -> Vec<String> {
+    match set {
+        PronounSet::Defined { .. } if set.language().is_some_and(|code| !is_english(code)) => {
+            Vec::new()
+        }
+        PronounSet::Defined { definition, .. } => definition_sentences(definition),
+        PronounSet::Any => {
+            vec!["Any pronouns are fine, such as she/her, he/him or they/them.".to_string()]
+        }
+        PronounSet::None => vec!["Refer to them by name instead of with pronouns.".to_string()],
+    }
+}
+
+/// A sentence for each form of `definition`, skipping the forms that aren't known.
+///
+/// The sentences are in the past tense, so they read the same for singular and plural sets.
+pub fn definition_sentences(definition: &PronounDef) -> Vec<String> {
+    let mut sentences = vec![
+        format!("{} went to the park.", capitalize(definition.subject())),
+        format!("I went with {}.", definition.object()),
+    ];
+
+    if let Some(determiner) = definition.possessive_determiner() {
+        sentences.push(format!("{} bike is red.", capitalize(determiner)));
+    }
+    if let Some(pronoun) = definition.possessive_pronoun() {
+        sentences.push(format!("The bike is {}.", pronoun));
+    }
+    if let Some(reflexive) = definition.reflexive() {
+        sentences.push(format!(
+            "{} rode the bike {}.",
+            capitalize(definition.subject()),
+            reflexive
+        ));
+    }
+
+    sentences
+}
+
+fn is_english(code: &str) -> bool {
+    crate::language::language(code) == Some(&crate::language::ENGLISH)
+}
+
+fn capitalize(word: &str) -> String {
+    let mut chars = word.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_record;
+
+    fn record_sentences(record: &str) -> Vec<String> {
+        sentences(&parse_record(record).unwrap().set.unwrap())
+    }
+
+    #[test]
+    fn test_common_sets() {
+        assert_eq!(
+            record_sentences("they/them"),
+            vec![
+                "They went to the park.",
+                "I went with them.",
+                "Their bike is red.",
+                "The bike is theirs.",
+                "They rode the bike themself.",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_unknown_forms() {
+        assert_eq!(
+            record_sentences("xe/xem"),
+            vec!["Xe went to the park.", "I went with xem."]
+        );
+    }
+
+    #[test]
+    fn test_any_and_none() {
+        assert_eq!(record_sentences("*").len(), 1);
+        assert!(record_sentences("!")[0].contains("by name"));
+    }
+
+    #[test]
+    fn test_other_languages() {
+        assert_eq!(record_sentences("er/ihn; lang=de"), Vec::<String>::new());
+        assert_eq!(record_sentences("she/her; lang=en-gb").len(), 5);
+    }
+
+    #[test]
+    fn test_capitalize() {
+        assert_eq!(capitalize("élle"), "Élle");
+        assert_eq!(capitalize(""), "");
+    }
+}
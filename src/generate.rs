@@ -0,0 +1,459 @@
+//! Generates the DNS records to publish pronouns with, for pasting into a zone or feeding to
+//! the API of a DNS provider.
+
+use std::fmt::{Display, Write};
+use std::str::FromStr;
+
+use crate::PRONOUNS_LABEL;
+use crate::zone::PronounZone;
+
+/// Longest character-string in a TXT record, longer records are split over several.
+const MAX_STRING_LEN: usize = 255;
+
+/// What to generate the records for.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ExportFormat {
+    /// Lines of a BIND zone file, see [`zone_file`]
+    #[default]
+    Bind,
+    /// The body of a Cloudflare batch request, see [`cloudflare_batch`]
+    Cloudflare,
+    /// A Route53 change batch, see [`route53_change_batch`]
+    Route53,
+    /// Terraform resources, see [`terraform`]
+    Terraform,
+}
+
+impl ExportFormat {
+    pub const ALL: [ExportFormat; 4] = [
+        ExportFormat::Bind,
+        ExportFormat::Cloudflare,
+        ExportFormat::Route53,
+        ExportFormat::Terraform,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            ExportFormat::Bind => "bind",
+            ExportFormat::Cloudflare => "cloudflare",
+            ExportFormat::Route53 => "route53",
+            ExportFormat::Terraform => "terraform",
+        }
+    }
+}
+
+impl Display for ExportFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+impl FromStr for ExportFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        ExportFormat::ALL
+            .into_iter()
+            .find(|format| format.name().eq_ignore_ascii_case(s))
+            .ok_or_else(|| format!("unknown export format {:?}", s))
+    }
+}
+
+/// Generates the records of every domain in `zone` in the given format.
+pub fn export(zone: &PronounZone, format: ExportFormat) -> String {
+    match format {
+        ExportFormat::Bind => zone_file(zone),
+        ExportFormat::Cloudflare => cloudflare_batch(zone),
+        ExportFormat::Route53 => route53_change_batch(zone),
+        ExportFormat::Terraform => terraform(zone),
+    }
+}
+
+/// Formats the records of every domain in `zone` as BIND zone file lines, such as
+///
+/// ```text
+/// pronouns.kinda.red. 3600 IN TXT "she/her; preferred"
+/// ```
+///
+/// Owner names are fully qualified, so the lines can be pasted into any zone file.
+pub fn zone_file(zone: &PronounZone) -> String {
+    let mut out = String::new();
+
+    for (domain, records) in zone.domains() {
+        for record in records {
+            // writing to a String can't fail
+            let _ = writeln!(
+                out,
+                "{}.{}. {} IN TXT {}",
+                PRONOUNS_LABEL,
+                domain,
+                zone.ttl(),
+                txt_rdata(record)
+            );
+        }
+    }
+
+    out
+}
+
+/// Formats the records as the JSON body of a request to the Cloudflare
+/// [batch DNS records endpoint](https://developers.cloudflare.com/api/resources/dns/subresources/records/methods/batch/),
+/// `POST /zones/{zone_id}/dns_records/batch`, which creates all of them at once.
+pub fn cloudflare_batch(zone: &PronounZone) -> String {
+    let mut posts = Vec::new();
+
+    for (domain, records) in zone.domains() {
+        for record in records {
+            posts.push(format!(
+                "    {{\n      \"type\": \"TXT\",\n      \"name\": {},\n      \"content\": {},\n      \"ttl\": {}\n    }}",
+                json_string(&format!("{}.{}", PRONOUNS_LABEL, domain)),
+                json_string(&cloudflare_content(record)),
+                zone.ttl()
+            ));
+        }
+    }
+
+    format!("{{\n  \"posts\": [\n{}\n  ]\n}}\n", posts.join(",\n"))
+}
+
+/// Formats the records as a Route53 change batch, which upserts the TXT record set of each
+/// domain, for `aws route53 change-resource-record-sets --change-batch file://...`.
+pub fn route53_change_batch(zone: &PronounZone) -> String {
+    let mut changes = Vec::new();
+
+    for (domain, records) in zone.domains() {
+        let values = records
+            .iter()
+            .map(|record| {
+                format!(
+                    "          {{ \"Value\": {} }}",
+                    json_string(&route53_rdata(record))
+                )
+            })
+            .collect::<Vec<String>>();
+
+        changes.push(format!(
+            "    {{\n      \"Action\": \"UPSERT\",\n      \"ResourceRecordSet\": {{\n        \"Name\": {},\n        \"Type\": \"TXT\",\n        \"TTL\": {},\n        \"ResourceRecords\": [\n{}\n        ]\n      }}\n    }}",
+            json_string(&format!("{}.{}.", PRONOUNS_LABEL, domain)),
+            zone.ttl(),
+            values.join(",\n")
+        ));
+    }
+
+    format!(
+        "{{\n  \"Comment\": \"Pronoun records generated by podns\",\n  \"Changes\": [\n{}\n  ]\n}}\n",
+        changes.join(",\n")
+    )
+}
+
+/// Formats the records as `aws_route53_record` resources of the Terraform AWS provider, with
+/// the hosted zone taken from `var.zone_id`.
+pub fn terraform(zone: &PronounZone) -> String {
+    let mut resources = Vec::new();
+
+    for (domain, records) in zone.domains() {
+        let values = records
+            .iter()
+            // the provider quotes each value itself, and splits it into strings at `""`
+            .map(|record| {
+                let rdata = route53_rdata(record);
+                let value = rdata[1..rdata.len() - 1].replace("\" \"", "\"\"");
+                format!("    {},", hcl_string(&value))
+            })
+            .collect::<Vec<String>>();
+
+        resources.push(format!(
+            "resource \"aws_route53_record\" \"{}\" {{\n  zone_id = var.zone_id\n  name    = {}\n  type    = \"TXT\"\n  ttl     = {}\n  records = [\n{}\n  ]\n}}\n",
+            resource_name(domain),
+            hcl_string(&format!("{}.{}", PRONOUNS_LABEL, domain)),
+            zone.ttl(),
+            values.join("\n")
+        ));
+    }
+
+    resources.join("\n")
+}
+
+/// A Terraform resource name for the records of `domain`, like `pronouns_kinda_red`.
+fn resource_name(domain: &str) -> String {
+    format!("{}.{}", PRONOUNS_LABEL, domain)
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect()
+}
+
+fn json_string(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            c if c.is_control() => {
+                let _ = write!(out, "\\u{:04x}", c as u32);
+            }
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+fn hcl_string(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push('"');
+    let mut chars = s.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            // `${` and `%{` start template sequences
+            '$' | '%' if chars.peek() == Some(&'{') => {
+                out.push(c);
+                out.push(c);
+            }
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+/// How the characters of a record outside of printable ASCII are written in its RDATA.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Escape {
+    /// Each byte as `\DDD` in decimal, as zone files do
+    Decimal,
+    /// Each byte as `\ooo` in octal, as Route53 does
+    Octal,
+    /// As they are, for APIs that take text
+    Raw,
+}
+
+/// Formats `record` as the RDATA of a TXT record in zone file syntax.
+///
+/// The record is split into quoted character-strings of at most 255 bytes, with quotes and
+/// backslashes escaped, and bytes outside of printable ASCII written as `\DDD`.
+pub fn txt_rdata(record: &str) -> String {
+    quoted_strings(record, Escape::Decimal)
+}
+
+/// Formats `record` as the value of a Route53 TXT record, which is like [`txt_rdata`] but with
+/// the bytes outside of printable ASCII written in octal, as `\ooo`.
+fn route53_rdata(record: &str) -> String {
+    quoted_strings(record, Escape::Octal)
+}
+
+/// Formats `record` as the content of a Cloudflare TXT record, where only quotes and
+/// backslashes are escaped, since the content is text rather than zone file syntax.
+fn cloudflare_content(record: &str) -> String {
+    quoted_strings(record, Escape::Raw)
+}
+
+fn quoted_strings(record: &str, escape: Escape) -> String {
+    let mut out = String::with_capacity(record.len() + 2);
+    for (i, chunk) in character_strings(record).into_iter().enumerate() {
+        if i > 0 {
+            out.push(' ');
+        }
+
+        out.push('"');
+        for c in chunk.chars() {
+            match (c, escape) {
+                ('"' | '\\', _) => {
+                    out.push('\\');
+                    out.push(c);
+                }
+                (' '..='~', _) | (_, Escape::Raw) => out.push(c),
+                (_, Escape::Decimal) => {
+                    for b in c.encode_utf8(&mut [0; 4]).bytes() {
+                        let _ = write!(out, "\\{:03}", b);
+                    }
+                }
+                (_, Escape::Octal) => {
+                    for b in c.encode_utf8(&mut [0; 4]).bytes() {
+                        let _ = write!(out, "\\{:03o}", b);
+                    }
+                }
+            }
+        }
+        out.push('"');
+    }
+
+    out
+}
+
+/// Splits `record` into character-strings of at most 255 bytes, without splitting a character,
+/// so each of them is text on its own.
+fn character_strings(record: &str) -> Vec<&str> {
+    let mut strings = Vec::new();
+    let mut rest = record;
+
+    while rest.len() > MAX_STRING_LEN {
+        let mut end = MAX_STRING_LEN;
+        while !rest.is_char_boundary(end) {
+            end -= 1;
+        }
+        let (string, tail) = rest.split_at(end);
+        strings.push(string);
+        rest = tail;
+    }
+    strings.push(rest);
+
+    strings
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::*;
+
+    // compares `actual` with the file in `src/snapshots`, run with `UPDATE_SNAPSHOTS=1` to
+    // write the new output instead
+    fn assert_snapshot(name: &str, actual: &str) {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("src/snapshots")
+            .join(name);
+
+        if std::env::var_os("UPDATE_SNAPSHOTS").is_some() {
+            std::fs::write(&path, actual).unwrap();
+            return;
+        }
+
+        let expected = std::fs::read_to_string(&path)
+            .unwrap_or_else(|e| panic!("failed to read snapshot {}: {}", path.display(), e));
+        assert_eq!(
+            actual, expected,
+            "{} changed, run with UPDATE_SNAPSHOTS=1 to update it",
+            name
+        );
+    }
+
+    fn export_zone() -> PronounZone {
+        let mut zone = PronounZone::new(3600);
+        zone.insert(
+            "kinda.red",
+            vec![
+                "she/her; preferred".to_string(),
+                "they/them # \"sometimes\", costs ${0}".to_string(),
+                "sie/sie; lang=de # für Deutsch".to_string(),
+            ],
+        )
+        .unwrap();
+        zone.insert(
+            "example.org",
+            vec![format!(
+                "ze/hir # {}",
+                "long comment ".repeat(20).trim_end()
+            )],
+        )
+        .unwrap();
+        zone
+    }
+
+    #[test]
+    fn test_export_snapshots() {
+        let zone = export_zone();
+        assert_snapshot("export.bind", &export(&zone, ExportFormat::Bind));
+        assert_snapshot(
+            "export_cloudflare.json",
+            &export(&zone, ExportFormat::Cloudflare),
+        );
+        assert_snapshot("export_route53.json", &export(&zone, ExportFormat::Route53));
+        assert_snapshot("export.tf", &export(&zone, ExportFormat::Terraform));
+    }
+
+    #[test]
+    fn test_json_exports_are_valid() {
+        let zone = export_zone();
+
+        // the contents are the records as text, once decoded from JSON
+        let cloudflare = cloudflare_batch(&zone);
+        assert!(cloudflare.contains(&json_string(&cloudflare_content(
+            &zone.domains().next().unwrap().1[0]
+        ))));
+        assert!(cloudflare.contains(r#""content": "\"sie/sie; lang=de # für Deutsch\"""#));
+        let route53 = route53_change_batch(&zone);
+        assert!(route53.contains("\"Name\": \"pronouns.kinda.red.\""));
+        assert!(route53.contains(r#"f\\303\\274r"#));
+
+        for json in [cloudflare, route53] {
+            serde_json::from_str::<serde_json::Value>(&json).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_escapes() {
+        assert_eq!(json_string("a\"b\\c\u{1}"), r#""a\"b\\c\u0001""#);
+        assert_eq!(hcl_string("${x} %{y} $z \""), r#""$${x} %%{y} $z \"""#);
+        assert_eq!(
+            resource_name("kinda-red.example"),
+            "pronouns_kinda_red_example"
+        );
+        assert_eq!("Route53".parse::<ExportFormat>(), Ok(ExportFormat::Route53));
+        assert!("zonefile".parse::<ExportFormat>().is_err());
+    }
+
+    #[test]
+    fn test_zone_file() {
+        let mut zone = PronounZone::new(3600);
+        zone.insert(
+            "kinda.red",
+            vec!["she/her; preferred".to_string(), "they/them".to_string()],
+        )
+        .unwrap();
+        zone.insert("Example.org", vec!["he/him # \"he\" is fine".to_string()])
+            .unwrap();
+
+        assert_eq!(
+            zone_file(&zone),
+            "pronouns.example.org. 3600 IN TXT \"he/him # \\\"he\\\" is fine\"\n\
+             pronouns.kinda.red. 3600 IN TXT \"she/her; preferred\"\n\
+             pronouns.kinda.red. 3600 IN TXT \"they/them\"\n"
+        );
+    }
+
+    #[test]
+    fn test_txt_rdata_escapes() {
+        assert_eq!(txt_rdata("she/her"), r#""she/her""#);
+        assert_eq!(txt_rdata(r#"a\b"c"#), r#""a\\b\"c""#);
+        assert_eq!(txt_rdata("ze/zir # über"), r#""ze/zir # \195\188ber""#);
+        assert_eq!(txt_rdata("tab\there"), r#""tab\009here""#);
+        assert_eq!(txt_rdata(""), r#""""#);
+
+        // Route53 reads escapes as octal, and Cloudflare takes text
+        assert_eq!(route53_rdata("ze/zir # über"), r#""ze/zir # \303\274ber""#);
+        assert_eq!(route53_rdata("tab\there"), r#""tab\011here""#);
+        assert_eq!(
+            cloudflare_content("ze/zir # \"über\""),
+            r#""ze/zir # \"über\"""#
+        );
+    }
+
+    #[test]
+    fn test_txt_rdata_chunks() {
+        // 300 bytes
+        let record = format!("they/them # {}", "a".repeat(288));
+        let rdata = txt_rdata(&record);
+
+        let (first, second) = rdata.split_once("\" \"").unwrap();
+        assert_eq!(first.len(), 1 + 255);
+        assert_eq!(second.len(), 45 + 1);
+        assert_eq!(
+            format!("{}{}", first.trim_matches('"'), second.trim_matches('"')),
+            record
+        );
+
+        assert_eq!(txt_rdata(&"a".repeat(255)).matches('"').count(), 2);
+        assert_eq!(txt_rdata(&"a".repeat(256)).matches('"').count(), 4);
+
+        // characters aren't split between strings
+        let record = format!("{}ü", "a".repeat(254));
+        assert_eq!(
+            cloudflare_content(&record),
+            format!("\"{}\" \"ü\"", "a".repeat(254))
+        );
+    }
+}
@@ -0,0 +1,238 @@
+//! Guesses the forms a pronoun record leaves out, for sets that aren't common enough to be
+//! inferred with certainty.
+//!
+//! The guesses are never part of a [`PronounDef`], so they can be offered as suggestions
+//! without being mistaken for what someone declared.
+//!
+//! ```
+//! use podns::PronounForm;
+//! use podns::guess::guess_missing_forms;
+//!
+//! let record = podns::parse_record("xe/xem").unwrap();
+//! let Some(podns::PronounSet::Defined { definition, .. }) = &record.set else {
+//!     unreachable!()
+//! };
+//!
+//! let guesses = guess_missing_forms(definition);
+//! assert_eq!(guesses[0].form, PronounForm::PossessiveDeterminer);
+//! assert_eq!(guesses[0].value, "xyr");
+//! assert_eq!(guesses[2].value, "xemself");
+//! ```
+
+use crate::pronouns::{PronounDef, PronounForm, Provenance};
+
+/// Sets whose forms are well established, as subject, object, possessive determiner,
+/// possessive pronoun and reflexive.
+const KNOWN_SETS: [[&str; 5]; 12] = [
+    ["xe", "xem", "xyr", "xyrs", "xemself"],
+    ["ze", "hir", "hir", "hirs", "hirself"],
+    ["ze", "zir", "zir", "zirs", "zirself"],
+    ["zie", "zim", "zir", "zirs", "zirself"],
+    ["ey", "em", "eir", "eirs", "emself"],
+    ["e", "em", "eir", "eirs", "emself"],
+    ["fae", "faer", "faer", "faers", "faerself"],
+    ["ve", "ver", "vis", "vis", "verself"],
+    ["per", "per", "pers", "pers", "perself"],
+    ["thon", "thon", "thons", "thons", "thonself"],
+    ["co", "co", "cos", "cos", "coself"],
+    ["ne", "nem", "nir", "nirs", "nemself"],
+];
+
+/// Confidence of a form taken from [`KNOWN_SETS`].
+const KNOWN_SET_CONFIDENCE: f32 = 0.9;
+
+/// A likely value for a form that a record leaves out.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Guess {
+    pub form: PronounForm,
+    pub value: String,
+    /// From 0 to 1, how likely the guess is to be right
+    pub confidence: f32,
+}
+
+/// Guesses every form of `definition` that is neither given nor inferred from a common set.
+///
+/// Sets that are well known, like xe/xem, are completed from a table. Other sets are completed
+/// with the patterns those sets follow, such as the reflexive being the object followed by
+/// `self`, with a lower confidence.
+pub fn guess_missing_forms(definition: &PronounDef) -> Vec<Guess> {
+    let forms = definition.forms();
+    let known = |form: PronounForm| {
+        forms
+            .iter()
+            .find(|(f, resolved)| *f == form && resolved.provenance != Provenance::Unknown)
+            .and_then(|(_, resolved)| resolved.value)
+    };
+
+    // a known set, as long as it agrees with every form that was given
+    let known_set = KNOWN_SETS.iter().find(|set| {
+        PronounForm::ALL
+            .iter()
+            .zip(set.iter())
+            .all(|(form, value)| known(*form).is_none_or(|given| given == *value))
+    });
+
+    let mut guesses: Vec<Guess> = Vec::new();
+    for &(form, resolved) in &forms {
+        if resolved.provenance != Provenance::Unknown {
+            continue;
+        }
+
+        let guess = match known_set {
+            Some(set) => Some((set[form_index(form)].to_string(), KNOWN_SET_CONFIDENCE)),
+            None => {
+                // later forms build on the guesses for earlier ones
+                let guessed = |form: PronounForm| {
+                    guesses
+                        .iter()
+                        .find(|guess| guess.form == form)
+                        .map(|guess| (guess.value.as_str(), guess.confidence))
+                };
+                let value = |form| known(form).map(|value| (value, 1.0)).or(guessed(form));
+
+                match form {
+                    PronounForm::PossessiveDeterminer => {
+                        guess_determiner(definition.subject(), definition.object())
+                    }
+                    PronounForm::PossessivePronoun => {
+                        value(PronounForm::PossessiveDeterminer).map(|(determiner, confidence)| {
+                            let pronoun = if determiner.ends_with('s') {
+                                determiner.to_string()
+                            } else {
+                                format!("{}s", determiner)
+                            };
+                            (pronoun, confidence * 0.9)
+                        })
+                    }
+                    PronounForm::Reflexive => Some((format!("{}self", definition.object()), 0.7)),
+                    // always given
+                    PronounForm::Subject | PronounForm::Object => None,
+                }
+            }
+        };
+
+        if let Some((value, confidence)) = guess {
+            guesses.push(Guess {
+                form,
+                value,
+                confidence,
+            });
+        }
+    }
+
+    guesses
+}
+
+fn form_index(form: PronounForm) -> usize {
+    PronounForm::ALL
+        .iter()
+        .position(|f| *f == form)
+        .expect("every form is in ALL")
+}
+
+/// The possessive determiner of a set that isn't known, from the patterns of the known ones.
+fn guess_determiner(subject: &str, object: &str) -> Option<(String, f32)> {
+    if subject == object {
+        // like per/per/pers and thon/thon/thons
+        return Some((format!("{}s", subject), 0.5));
+    }
+    if object.ends_with('r') {
+        // like hir and faer
+        return Some((object.to_string(), 0.6));
+    }
+    if let Some(stem) = object.strip_suffix("em") {
+        // like xem/xyr and em/eir
+        let determiner = if stem.is_empty() {
+            "eir".to_string()
+        } else {
+            format!("{}yr", stem)
+        };
+        return Some((determiner, 0.5));
+    }
+
+    Some((format!("{}r", subject.trim_end_matches('e')), 0.3))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn guesses(subject: &str, object: &str, determiner: Option<&str>) -> Vec<(String, f32)> {
+        let definition = PronounDef::new(
+            subject.to_string(),
+            object.to_string(),
+            determiner.map(String::from),
+            None,
+            None,
+        );
+        guess_missing_forms(&definition)
+            .into_iter()
+            .map(|guess| (guess.value, guess.confidence))
+            .collect()
+    }
+
+    #[test]
+    fn test_known_sets() {
+        assert_eq!(
+            guesses("fae", "faer", None),
+            vec![
+                ("faer".to_string(), 0.9),
+                ("faers".to_string(), 0.9),
+                ("faerself".to_string(), 0.9)
+            ]
+        );
+        // the given forms pick between sets with the same subject
+        assert_eq!(guesses("ze", "zir", None)[2].0, "zirself");
+    }
+
+    #[test]
+    fn test_patterns() {
+        let values = |guesses: Vec<(String, f32)>| {
+            guesses
+                .into_iter()
+                .map(|(value, _)| value)
+                .collect::<Vec<String>>()
+        };
+
+        assert_eq!(
+            values(guesses("kit", "kit", None)),
+            vec!["kits", "kits", "kitself"]
+        );
+        assert_eq!(
+            values(guesses("le", "lir", None)),
+            vec!["lir", "lirs", "lirself"]
+        );
+        assert_eq!(
+            values(guesses("qe", "qem", None)),
+            vec!["qyr", "qyrs", "qemself"]
+        );
+
+        // a given form that doesn't match the known set
+        assert_eq!(
+            values(guesses("xe", "xem", Some("xir"))),
+            vec!["xirs", "xemself"]
+        );
+    }
+
+    #[test]
+    fn test_confidence() {
+        let guesses = guesses("bun", "bunny", None);
+        assert!(guesses.iter().all(|(_, confidence)| *confidence < 0.9));
+        // the possessive pronoun builds on the guessed determiner
+        assert!(guesses[1].1 < guesses[0].1);
+    }
+
+    #[test]
+    fn test_nothing_to_guess() {
+        assert_eq!(guesses("she", "her", None), vec![]);
+
+        let definition = PronounDef::new(
+            "xe".to_string(),
+            "xem".to_string(),
+            Some("xyr".to_string()),
+            Some("xyrs".to_string()),
+            Some("xemself".to_string()),
+        );
+        assert_eq!(guess_missing_forms(&definition), vec![]);
+    }
+}
@@ -0,0 +1,413 @@
+//! An HTTP API for looking up pronouns, for clients that can't do DNS lookups themselves,
+//! such as browsers.
+//!
+//! `GET /v1/pronouns/{domain}` responds with the pronoun records of `domain` as JSON:
+//!
+//! ```json
+//! {
+//!   "domain": "kinda.red",
+//!   "name": "pronouns.kinda.red",
+//!   "authenticated": false,
+//!   "records": [
+//!     {
+//!       "record": "she/her/her/hers/herself; preferred",
+//!       "set": {
+//!         "type": "defined",
+//!         "subject": "she",
+//!         "object": "her",
+//!         "possessive_determiner": "her",
+//!         "possessive_pronoun": "hers",
+//!         "reflexive": "herself",
+//!         "tags": ["preferred"]
+//!       },
+//!       "comment": null
+//!     }
+//!   ],
+//!   "profile": {
+//!     "preferred": "she/her",
+//!     "pronouns": ["she/her"],
+//!     "any": false,
+//!     "none": false,
+//!     "comments": []
+//!   }
+//! }
+//! ```
+//!
+//! Errors are responded to with a matching status code, and a body like
+//! `{"error": {"code": "not_found", "message": "..."}}`.
+
+use std::io;
+use std::net::SocketAddr;
+use std::sync::Arc;
+
+use serde_json::{Value, json};
+
+use crate::PRONOUNS_LABEL;
+use crate::dns::wire;
+use crate::lookup::{LookupOptions, PronounLookup, TxtResolver, lookup_pronouns_for_domain};
+use crate::output::record_json;
+use crate::pronouns::{PronounRecord, PronounSet, PronounTag};
+
+const API_PREFIX: &str = "/v1/pronouns/";
+
+/// How long responses are cached for when the resolver doesn't report a TTL.
+const DEFAULT_MAX_AGE: u32 = 300;
+
+/// How long a domain without pronoun records is cached for.
+const NOT_FOUND_MAX_AGE: u32 = 60;
+
+/// Serves the HTTP API, looking up the records with `resolver`.
+pub struct HttpServer {
+    server: tiny_http::Server,
+    resolver: Arc<dyn TxtResolver + Send + Sync>,
+}
+
+impl HttpServer {
+    pub fn bind(
+        addr: SocketAddr,
+        resolver: impl TxtResolver + Send + Sync + 'static,
+    ) -> io::Result<Self> {
+        let server = tiny_http::Server::http(addr).map_err(io::Error::other)?;
+
+        Ok(HttpServer {
+            server,
+            resolver: Arc::new(resolver),
+        })
+    }
+
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.server
+            .server_addr()
+            .to_ip()
+            .ok_or_else(|| io::Error::other("not listening on an IP address"))
+    }
+
+    /// Serves requests until the server is dropped, each on its own thread.
+    pub fn run(&self) {
+        for request in self.server.incoming_requests() {
+            let resolver = self.resolver.clone();
+
+            std::thread::spawn(move || {
+                let response = handle(&*resolver, request.method().as_str(), request.url());
+
+                let mut http_response = tiny_http::Response::from_string(response.body.to_string())
+                    .with_status_code(response.status)
+                    .with_header(header("Content-Type", "application/json"))
+                    .with_header(header("Cache-Control", &response.cache_control))
+                    // the API is meant to be used from browsers on other sites
+                    .with_header(header("Access-Control-Allow-Origin", "*"));
+                if response.status == 405 {
+                    http_response = http_response.with_header(header("Allow", "GET"));
+                }
+
+                // the client may have gone away in the meantime
+                let _ = request.respond(http_response);
+            });
+        }
+    }
+}
+
+impl std::fmt::Debug for HttpServer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("HttpServer")
+            .field("addr", &self.local_addr().ok())
+            .finish_non_exhaustive()
+    }
+}
+
+fn header(name: &str, value: &str) -> tiny_http::Header {
+    tiny_http::Header::from_bytes(name.as_bytes(), value.as_bytes())
+        .expect("header names and values are ASCII")
+}
+
+#[derive(Debug, PartialEq)]
+struct ApiResponse {
+    status: u16,
+    cache_control: String,
+    body: Value,
+}
+
+impl ApiResponse {
+    fn error(status: u16, code: &str, message: impl ToString) -> Self {
+        let cache_control = match status {
+            404 => format!("public, max-age={}", NOT_FOUND_MAX_AGE),
+            _ => "no-store".to_string(),
+        };
+
+        ApiResponse {
+            status,
+            cache_control,
+            body: json!({
+                "error": {
+                    "code": code,
+                    "message": message.to_string(),
+                }
+            }),
+        }
+    }
+}
+
+fn handle(resolver: &(dyn TxtResolver + Send + Sync), method: &str, url: &str) -> ApiResponse {
+    let path = url.split(['?', '#']).next().unwrap_or_default();
+
+    let Some(domain) = path.strip_prefix(API_PREFIX) else {
+        return ApiResponse::error(
+            404,
+            "unknown_endpoint",
+            format!("no such endpoint: {}", path),
+        );
+    };
+    if method != "GET" {
+        return ApiResponse::error(
+            405,
+            "method_not_allowed",
+            format!("{} is not allowed, use GET", method),
+        );
+    }
+
+    if !is_valid_domain(domain) {
+        return ApiResponse::error(
+            400,
+            "invalid_domain",
+            format!("invalid domain: {:?}", domain),
+        );
+    }
+
+    let lookup = match lookup_pronouns_for_domain(resolver, domain, &LookupOptions::default()) {
+        Ok(lookup) => lookup,
+        Err(e) => {
+            return match e.kind() {
+                io::ErrorKind::NotFound => ApiResponse::error(
+                    404,
+                    "not_found",
+                    format!("{}.{} does not exist", PRONOUNS_LABEL, domain),
+                ),
+                io::ErrorKind::TimedOut | io::ErrorKind::WouldBlock => {
+                    ApiResponse::error(504, "timeout", "the DNS lookup timed out")
+                }
+                _ => ApiResponse::error(502, "resolver_error", e),
+            };
+        }
+    };
+
+    if lookup.records.is_empty() {
+        return ApiResponse::error(
+            404,
+            "no_records",
+            format!("no valid pronoun records found for {}", domain),
+        );
+    }
+
+    ApiResponse {
+        status: 200,
+        cache_control: format!("public, max-age={}", lookup.ttl.unwrap_or(DEFAULT_MAX_AGE)),
+        body: lookup_json(domain, &lookup),
+    }
+}
+
+/// Only hostnames are looked up, so that the API can't be used to query arbitrary names.
+fn is_valid_domain(domain: &str) -> bool {
+    !domain.is_empty()
+        && domain
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || matches!(b, b'-' | b'_' | b'.'))
+        && wire::write_name(&mut Vec::new(), &format!("{}.{}", PRONOUNS_LABEL, domain)).is_ok()
+}
+
+fn lookup_json(domain: &str, lookup: &PronounLookup) -> Value {
+    json!({
+        "domain": domain,
+        "name": lookup.name,
+        "authenticated": lookup.authenticated,
+        "records": lookup.records.iter().map(record_json).collect::<Vec<Value>>(),
+        "profile": profile_json(&lookup.records),
+    })
+}
+
+/// A summary of the records, for showing next to someone's name.
+fn profile_json(records: &[PronounRecord]) -> Value {
+    let mut preferred = None;
+    let mut pronouns = Vec::new();
+    let mut any = false;
+    let mut none = false;
+
+    // the records are sorted with preferred sets first
+    for record in records {
+        match &record.set {
+            Some(PronounSet::Defined { definition, tags }) => {
+                let short = format!("{}/{}", definition.subject(), definition.object());
+                if preferred.is_none() && tags.contains(&PronounTag::Preferred) {
+                    preferred = Some(short.clone());
+                }
+                if !pronouns.contains(&short) {
+                    pronouns.push(short);
+                }
+            }
+            Some(PronounSet::Any) => any = true,
+            Some(PronounSet::None) => none = true,
+            None => {}
+        }
+    }
+
+    json!({
+        "preferred": preferred,
+        "pronouns": pronouns,
+        "any": any,
+        "none": none,
+        "comments": records
+            .iter()
+            .filter_map(|record| record.comment.as_deref())
+            .collect::<Vec<&str>>(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Read, Write};
+    use std::net::TcpStream;
+
+    use super::*;
+    use crate::testing::StaticResolver;
+
+    fn resolver() -> StaticResolver {
+        StaticResolver::new()
+            .with_records(
+                "pronouns.kinda.red",
+                ["she/her; preferred", "they/them # only sometimes", "*"],
+            )
+            .with_records("pronouns.invalid.example", ["not/valid/at/all/really/no"])
+            .with_timeout("pronouns.slow.example")
+            .with_server_failure("pronouns.broken.example")
+    }
+
+    #[test]
+    fn test_lookup() {
+        let response = handle(&resolver(), "GET", "/v1/pronouns/kinda.red");
+        assert_eq!(response.status, 200);
+        assert_eq!(response.cache_control, "public, max-age=300");
+
+        let body = response.body;
+        assert_eq!(body["domain"], "kinda.red");
+        assert_eq!(body["name"], "pronouns.kinda.red");
+        assert_eq!(body["records"].as_array().unwrap().len(), 3);
+        assert_eq!(body["records"][0]["set"]["subject"], "she");
+        assert_eq!(body["records"][0]["set"]["reflexive"], "herself");
+        assert_eq!(body["records"][0]["set"]["tags"], json!(["preferred"]));
+        assert_eq!(body["records"][1]["comment"], "only sometimes");
+        assert_eq!(body["records"][2]["set"], json!({ "type": "any" }));
+        assert_eq!(
+            body["profile"],
+            json!({
+                "preferred": "she/her",
+                "pronouns": ["she/her", "they/them"],
+                "any": true,
+                "none": false,
+                "comments": ["only sometimes"],
+            })
+        );
+    }
+
+    #[test]
+    fn test_errors() {
+        let resolver = resolver();
+        let error = |method, url| {
+            let response = handle(&resolver, method, url);
+            (response.status, response.body["error"]["code"].clone())
+        };
+
+        assert_eq!(
+            error("GET", "/v1/pronouns/missing.example"),
+            (404, json!("not_found"))
+        );
+        assert_eq!(
+            error("GET", "/v1/pronouns/invalid.example"),
+            (404, json!("no_records"))
+        );
+        assert_eq!(
+            error("GET", "/v1/pronouns/slow.example"),
+            (504, json!("timeout"))
+        );
+        assert_eq!(
+            error("GET", "/v1/pronouns/broken.example"),
+            (502, json!("resolver_error"))
+        );
+        assert_eq!(
+            error("GET", "/v1/pronouns/"),
+            (400, json!("invalid_domain"))
+        );
+        assert_eq!(
+            error("GET", "/v1/pronouns/a..b"),
+            (400, json!("invalid_domain"))
+        );
+        assert_eq!(
+            error("GET", "/v1/pronouns/a b"),
+            (400, json!("invalid_domain"))
+        );
+        assert_eq!(
+            error("POST", "/v1/pronouns/kinda.red"),
+            (405, json!("method_not_allowed"))
+        );
+        assert_eq!(
+            error("GET", "/v2/pronouns/kinda.red"),
+            (404, json!("unknown_endpoint"))
+        );
+
+        assert_eq!(
+            handle(&resolver, "GET", "/v1/pronouns/slow.example").cache_control,
+            "no-store"
+        );
+    }
+
+    #[test]
+    fn test_server() {
+        let server = HttpServer::bind("127.0.0.1:0".parse().unwrap(), resolver()).unwrap();
+        let addr = server.local_addr().unwrap();
+        std::thread::spawn(move || server.run());
+
+        let mut stream = TcpStream::connect(addr).unwrap();
+        write!(
+            stream,
+            "GET /v1/pronouns/kinda.red?source=test HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n"
+        )
+        .unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+
+        assert!(response.starts_with("HTTP/1.1 200"), "{}", response);
+        let lower = response.to_lowercase();
+        assert!(lower.contains("content-type: application/json"));
+        assert!(lower.contains("cache-control: public, max-age=300"));
+        assert!(lower.contains("access-control-allow-origin: *"));
+
+        let body: Value = serde_json::from_str(response.split("\r\n\r\n").nth(1).unwrap()).unwrap();
+        assert_eq!(body["profile"]["preferred"], "she/her");
+    }
+
+    #[test]
+    fn test_records_match_cli_output() {
+        let resolver = resolver();
+        let body = handle(&resolver, "GET", "/v1/pronouns/kinda.red").body;
+
+        let txt = resolver.lookup_txt("pronouns.kinda.red").unwrap();
+        let report = crate::output::DomainReport::from_lookup("kinda.red", txt);
+        let cli: Value = serde_json::from_str(&crate::output::format_reports(
+            &[report],
+            crate::output::OutputFormat::Json,
+        ))
+        .unwrap();
+
+        // the API sorts the records, so match them up by their text
+        let records = body["records"].as_array().unwrap();
+        assert_eq!(records.len(), 3);
+        for record in records {
+            let parsed = cli[0]["records"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|cli| &cli["parsed"])
+                .find(|parsed| parsed["record"] == record["record"])
+                .unwrap();
+            assert_eq!(parsed, record);
+        }
+    }
+}
@@ -0,0 +1,228 @@
+//! Text for people rather than resolvers, like "any pronouns" instead of `*`.
+//!
+//! The text comes from message catalogs in `src/messages`, which are embedded in the crate.
+//! Locales without a catalog, and messages missing from one, fall back to English.
+//!
+//! ```
+//! use podns::human::HumanDisplay;
+//!
+//! let record = podns::parse_record("they/them; preferred; plural").unwrap();
+//! let set = record.set.unwrap();
+//! assert_eq!(set.human("en").to_string(), "they/them (preferred, plural)");
+//! assert_eq!(set.human("de-CH").to_string(), "they/them (bevorzugt, Plural)");
+//! assert_eq!(podns::PronounSet::Any.human("es").to_string(), "cualquier pronombre");
+//! ```
+
+use std::fmt::{Display, Formatter, Result};
+
+use crate::pronouns::{PronounRecord, PronounSet, PronounTag};
+
+/// Messages for one locale, as `key = value` lines.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Catalog {
+    /// The primary language subtag the catalog is for, like `de`
+    pub locale: &'static str,
+    messages: &'static str,
+}
+
+pub static ENGLISH: Catalog = Catalog {
+    locale: "en",
+    messages: include_str!("messages/en.txt"),
+};
+
+/// Every catalog in the crate.
+pub static CATALOGS: [&Catalog; 4] = [
+    &ENGLISH,
+    &Catalog {
+        locale: "de",
+        messages: include_str!("messages/de.txt"),
+    },
+    &Catalog {
+        locale: "es",
+        messages: include_str!("messages/es.txt"),
+    },
+    &Catalog {
+        locale: "fr",
+        messages: include_str!("messages/fr.txt"),
+    },
+];
+
+/// The catalog for a locale like `de` or `es-MX`, or the English one if there is none.
+pub fn catalog(locale: &str) -> &'static Catalog {
+    let primary = locale.split(['-', '_']).next().unwrap_or_default();
+    CATALOGS
+        .iter()
+        .copied()
+        .find(|catalog| catalog.locale.eq_ignore_ascii_case(primary))
+        .unwrap_or(&ENGLISH)
+}
+
+impl Catalog {
+    /// The message for `key`, from English if this catalog doesn't have it.
+    pub fn message(&self, key: &str) -> Option<&'static str> {
+        self.lookup(key).or_else(|| ENGLISH.lookup(key))
+    }
+
+    fn lookup(&self, key: &str) -> Option<&'static str> {
+        self.messages
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.starts_with('#'))
+            .filter_map(|line| line.split_once('='))
+            .find(|(name, _)| name.trim() == key)
+            .map(|(_, message)| message.trim())
+    }
+}
+
+/// Formats a value for people, in the language of a [`Catalog`].
+pub trait HumanDisplay {
+    fn fmt_human(&self, catalog: &Catalog, f: &mut Formatter<'_>) -> Result;
+
+    /// Wraps the value, so it can be formatted with `{}` for `locale`.
+    fn human(&self, locale: &str) -> Human<'_, Self> {
+        Human {
+            value: self,
+            catalog: catalog(locale),
+        }
+    }
+}
+
+/// A value that formats with [`HumanDisplay`], returned by [`HumanDisplay::human`].
+pub struct Human<'a, T: ?Sized> {
+    value: &'a T,
+    catalog: &'static Catalog,
+}
+
+impl<T: HumanDisplay + ?Sized> Display for Human<'_, T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        self.value.fmt_human(self.catalog, f)
+    }
+}
+
+impl HumanDisplay for PronounTag {
+    fn fmt_human(&self, catalog: &Catalog, f: &mut Formatter<'_>) -> Result {
+        let message = match self {
+            PronounTag::Preferred => catalog.message("preferred"),
+            PronounTag::Plural => catalog.message("plural"),
+            // the name of the language, or the tag itself for languages without one
+            PronounTag::Language(code) => {
+                let primary = code.split('-').next().unwrap_or_default();
+                catalog.message(&format!("lang.{}", primary))
+            }
+        };
+
+        match message {
+            Some(message) => write!(f, "{}", message),
+            None => write!(f, "{}", self),
+        }
+    }
+}
+
+/// The parts that were given, followed by the tags in brackets, like `she/her (preferred)`.
+impl HumanDisplay for PronounSet {
+    fn fmt_human(&self, catalog: &Catalog, f: &mut Formatter<'_>) -> Result {
+        let message = match self {
+            PronounSet::Defined { definition, tags } => {
+                let parts = [
+                    Some(&definition.subject),
+                    Some(&definition.object),
+                    definition.possessive_determiner.as_ref(),
+                    definition.possessive_pronoun.as_ref(),
+                    definition.reflexive.as_ref(),
+                ];
+                let parts = parts
+                    .into_iter()
+                    .map_while(|part| part.map(String::as_str))
+                    .collect::<Vec<_>>();
+                write!(f, "{}", parts.join("/"))?;
+
+                for (i, tag) in tags.iter().enumerate() {
+                    let separator = if i == 0 { " (" } else { ", " };
+                    write!(f, "{}", separator)?;
+                    tag.fmt_human(catalog, f)?;
+                }
+                if !tags.is_empty() {
+                    write!(f, ")")?;
+                }
+                return Ok(());
+            }
+            PronounSet::Any => catalog.message("any"),
+            PronounSet::None => catalog.message("none"),
+        };
+
+        write!(f, "{}", message.unwrap_or_default())
+    }
+}
+
+/// The set, followed by the comment after a dash.
+impl HumanDisplay for PronounRecord {
+    fn fmt_human(&self, catalog: &Catalog, f: &mut Formatter<'_>) -> Result {
+        if let Some(set) = &self.set {
+            set.fmt_human(catalog, f)?;
+        }
+
+        match (&self.set, &self.comment) {
+            (Some(_), Some(comment)) => write!(f, " — {}", comment),
+            (None, Some(comment)) => write!(f, "{}", comment),
+            (_, None) => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_record;
+
+    fn human(record: &str, locale: &str) -> String {
+        parse_record(record).unwrap().human(locale).to_string()
+    }
+
+    #[test]
+    fn test_any_and_none() {
+        assert_eq!(human("*", "en"), "any pronouns");
+        assert_eq!(human("!", "en"), "no pronouns — use my name");
+        assert_eq!(human("!", "de"), "keine Pronomen — nutze meinen Namen");
+        assert_eq!(human("*", "fr-CA"), "n'importe quels pronoms");
+    }
+
+    #[test]
+    fn test_tags() {
+        assert_eq!(
+            human("er/ihn; preferred; lang=de # hallo", "de"),
+            "er/ihn (bevorzugt, Deutsch) — hallo"
+        );
+        assert_eq!(human("elle/le; lang=es", "en"), "elle/le (Spanish)");
+        // languages without a name are shown as the tag
+        assert_eq!(human("hen/hen; lang=sv", "en"), "hen/hen (lang=sv)");
+    }
+
+    #[test]
+    fn test_fallback() {
+        // unknown locales are English
+        assert_eq!(catalog("ja"), &ENGLISH);
+        assert_eq!(human("she/her; plural", "ja"), "she/her (plural)");
+        assert_eq!(human("# just a comment", "en"), "just a comment");
+    }
+
+    #[test]
+    fn test_catalogs_are_complete() {
+        // every message in English is translated
+        let keys = ENGLISH
+            .messages
+            .lines()
+            .filter(|line| !line.starts_with('#'))
+            .filter_map(|line| line.split_once('='))
+            .map(|(key, _)| key.trim());
+        for key in keys {
+            for catalog in CATALOGS {
+                assert!(
+                    catalog.lookup(key).is_some(),
+                    "{} has no {}",
+                    catalog.locale,
+                    key
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,282 @@
+//! Pronoun sets in languages other than English.
+//!
+//! A record says which language its set is in with a `lang=` tag, like `er/ihn; lang=de`.
+//! Sets without one are English. Each [`Language`] gives the five parts of a record its own
+//! meaning, and has its own common sets to infer the parts a record leaves out.
+//!
+//! ```
+//! use podns::language::language;
+//!
+//! let record = podns::parse_record("er/ihn; lang=de").unwrap();
+//! let set = record.set.unwrap();
+//! let german = language(set.language().unwrap()).unwrap();
+//!
+//! let podns::PronounSet::Defined { definition, .. } = &set else {
+//!     unreachable!()
+//! };
+//! let slots = german.resolve(definition);
+//! assert_eq!(slots[2].0, "dative");
+//! assert_eq!(slots[2].1.value, Some("ihm"));
+//! ```
+
+use crate::pronouns::{PronounDef, PronounForm, Provenance, ResolvedForm};
+
+/// A language with its own meaning for the parts of a record.
+#[derive(Debug, PartialEq, Eq, Hash)]
+pub struct Language {
+    /// The primary language subtag, like `de`
+    pub code: &'static str,
+    /// The name of the language, in English
+    pub name: &'static str,
+    /// What each of the five parts of a record is, in order
+    pub slots: [&'static str; 5],
+    /// Sets that are common enough to infer the parts a record leaves out, in the order of
+    /// [`Language::slots`]
+    pub common_sets: &'static [[&'static str; 5]],
+}
+
+pub static ENGLISH: Language = Language {
+    code: "en",
+    name: "English",
+    slots: [
+        "subject",
+        "object",
+        "possessive_determiner",
+        "possessive_pronoun",
+        "reflexive",
+    ],
+    // inferred with `CommonPronounDef` instead, see `Language::resolve`
+    common_sets: &[],
+};
+
+/// German, with the pronoun in each case and the possessive, like `er/ihn/ihm/sein/seiner`.
+pub static GERMAN: Language = Language {
+    code: "de",
+    name: "German",
+    slots: [
+        "nominative",
+        "accusative",
+        "dative",
+        "possessive",
+        "genitive",
+    ],
+    common_sets: &[
+        ["er", "ihn", "ihm", "sein", "seiner"],
+        ["sie", "sie", "ihr", "ihr", "ihrer"],
+        ["es", "es", "ihm", "sein", "seiner"],
+        ["xier", "xien", "xiem", "xies", "xieser"],
+        ["dey", "dem", "dem", "deren", "derer"],
+        ["hen", "hen", "hem", "hens", "hens"],
+    ],
+};
+
+/// Spanish, with the direct and indirect object told apart, like `elle/le/le/su/suye`.
+pub static SPANISH: Language = Language {
+    code: "es",
+    name: "Spanish",
+    slots: [
+        "subject",
+        "direct_object",
+        "indirect_object",
+        "possessive",
+        "possessive_pronoun",
+    ],
+    common_sets: &[
+        ["él", "lo", "le", "su", "suyo"],
+        ["ella", "la", "le", "su", "suya"],
+        ["elle", "le", "le", "su", "suye"],
+        ["ellos", "los", "les", "su", "suyos"],
+        ["ellas", "las", "les", "su", "suyas"],
+        ["elles", "les", "les", "su", "suyes"],
+    ],
+};
+
+/// Every language that is known.
+pub static LANGUAGES: [&Language; 3] = [&ENGLISH, &GERMAN, &SPANISH];
+
+/// The language of a `lang=` tag, ignoring everything after the primary subtag, so both
+/// `de` and `de-at` are German.
+pub fn language(code: &str) -> Option<&'static Language> {
+    let primary = code.split('-').next().unwrap_or_default();
+    LANGUAGES
+        .iter()
+        .copied()
+        .find(|language| language.code.eq_ignore_ascii_case(primary))
+}
+
+/// Every part of `definition` under the name of its slot, in the language of `code`, the
+/// set's `lang=` tag, or in English if it has none.
+///
+/// Sets in a language that isn't known only have the parts they give, under the English names,
+/// since the common sets of English say nothing about other languages.
+pub fn resolve_forms<'a>(
+    code: Option<&str>,
+    definition: &'a PronounDef,
+) -> [(&'static str, ResolvedForm<'a>); 5] {
+    match code.map_or(Some(&ENGLISH), language) {
+        Some(language) => language.resolve(definition),
+        None => PronounForm::ALL.map(|form| {
+            let resolved = definition.form(form);
+            let resolved = match resolved.provenance {
+                Provenance::Explicit => resolved,
+                _ => ResolvedForm {
+                    value: None,
+                    provenance: Provenance::Unknown,
+                },
+            };
+            (ENGLISH.slot(form), resolved)
+        }),
+    }
+}
+
+impl Language {
+    /// Every part of `definition` under the name of its slot in this language, with the
+    /// parts that weren't given inferred from a common set of this language.
+    pub fn resolve<'a>(
+        &'static self,
+        definition: &'a PronounDef,
+    ) -> [(&'static str, ResolvedForm<'a>); 5] {
+        if self == &ENGLISH {
+            return PronounForm::ALL.map(|form| (self.slot(form), definition.form(form)));
+        }
+
+        let given = PronounForm::ALL.map(|form| match form {
+            PronounForm::Subject => Some(definition.subject()),
+            PronounForm::Object => Some(definition.object()),
+            PronounForm::PossessiveDeterminer => definition.possessive_determiner.as_deref(),
+            PronounForm::PossessivePronoun => definition.possessive_pronoun.as_deref(),
+            PronounForm::Reflexive => definition.reflexive.as_deref(),
+        });
+
+        // a common set, as long as it agrees with every part that was given
+        let common = self.common_sets.iter().find(|set| {
+            given
+                .iter()
+                .zip(set.iter())
+                .all(|(given, value)| given.is_none_or(|given| given == *value))
+        });
+
+        let mut slots = PronounForm::ALL.map(|form| {
+            (
+                self.slot(form),
+                ResolvedForm {
+                    value: None,
+                    provenance: Provenance::Unknown,
+                },
+            )
+        });
+        for (i, (_, resolved)) in slots.iter_mut().enumerate() {
+            *resolved = match (given[i], common) {
+                (Some(value), _) => ResolvedForm {
+                    value: Some(value),
+                    provenance: Provenance::Explicit,
+                },
+                (None, Some(set)) => ResolvedForm {
+                    value: Some(set[i]),
+                    provenance: Provenance::InferredFromLanguage(self),
+                },
+                (None, None) => continue,
+            };
+        }
+
+        slots
+    }
+
+    /// The name of the slot that `form` is in this language, like `dative` for the third part
+    /// of a German record.
+    pub fn slot(&self, form: PronounForm) -> &'static str {
+        let index = PronounForm::ALL
+            .iter()
+            .position(|f| *f == form)
+            .expect("every form is in ALL");
+        self.slots[index]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_record;
+    use crate::pronouns::PronounSet;
+
+    fn resolve(record: &str) -> Vec<(&'static str, Option<String>, bool)> {
+        let set = parse_record(record).unwrap().set.unwrap();
+        let PronounSet::Defined { definition, .. } = &set else {
+            panic!("not a defined set");
+        };
+
+        resolve_forms(set.language(), definition)
+            .into_iter()
+            .map(|(slot, resolved)| {
+                (
+                    slot,
+                    resolved.value.map(String::from),
+                    resolved.provenance == Provenance::Explicit,
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_language_lookup() {
+        assert_eq!(language("de"), Some(&GERMAN));
+        assert_eq!(language("es-mx"), Some(&SPANISH));
+        assert_eq!(language("EN-gb"), Some(&ENGLISH));
+        assert_eq!(language("fr"), None);
+    }
+
+    #[test]
+    fn test_german() {
+        assert_eq!(
+            resolve("er/ihn; lang=de"),
+            vec![
+                ("nominative", Some("er".to_string()), true),
+                ("accusative", Some("ihn".to_string()), true),
+                ("dative", Some("ihm".to_string()), false),
+                ("possessive", Some("sein".to_string()), false),
+                ("genitive", Some("seiner".to_string()), false),
+            ]
+        );
+
+        // a given part that doesn't match a common set leaves the rest unknown
+        let unknown = resolve("er/ihn/ihr; lang=de");
+        assert_eq!(unknown[3], ("possessive", None, false));
+    }
+
+    #[test]
+    fn test_spanish() {
+        let slots = resolve("elle/le; lang=es");
+        assert_eq!(slots[1].0, "direct_object");
+        assert_eq!(
+            slots[4],
+            ("possessive_pronoun", Some("suye".to_string()), false)
+        );
+    }
+
+    #[test]
+    fn test_english() {
+        // untagged sets are English, inferred with `CommonPronounDef`
+        let slots = resolve("she/her");
+        assert_eq!(
+            slots[2],
+            ("possessive_determiner", Some("her".to_string()), false)
+        );
+        assert_eq!(resolve("she/her; lang=en"), slots);
+    }
+
+    #[test]
+    fn test_unknown_language() {
+        // nothing is inferred from English for a language that isn't known
+        assert_eq!(
+            resolve("she/her; lang=fr"),
+            vec![
+                ("subject", Some("she".to_string()), true),
+                ("object", Some("her".to_string()), true),
+                ("possessive_determiner", None, false),
+                ("possessive_pronoun", None, false),
+                ("reflexive", None, false),
+            ]
+        );
+        assert_eq!(resolve("il/le/son; lang=fr")[2].1.as_deref(), Some("son"));
+    }
+}
@@ -1,63 +1,217 @@
-#[cfg(feature = "dns_resolve")]
+mod dedup;
 mod dns;
+pub mod examples;
+pub mod generate;
+pub mod guess;
+#[cfg(feature = "http_server")]
+pub mod http;
+pub mod human;
+pub mod language;
+pub mod lint;
+mod lookup;
+pub mod order;
+pub mod output;
 mod parser;
 pub mod pronouns;
+#[cfg(any(test, feature = "testing"))]
+pub mod testing;
+pub mod zone;
 
+pub use dedup::dedup_records;
+#[cfg(feature = "dns_server")]
+pub use dns::DnsServer;
+#[cfg(feature = "doh")]
+pub use dns::doh::{DohFormat, DohResolver};
+#[cfg(feature = "dot")]
+pub use dns::dot::{DOT_PORT, DotResolver};
 #[cfg(feature = "dns_resolve")]
-pub use dns::query_txt;
+pub use dns::{SystemResolver, query_txt, query_txt_raw};
+pub use dns::{decode_txt_response, encode_txt_query};
+#[cfg(feature = "http_server")]
+pub use http::HttpServer;
+
+pub use lookup::{
+    InvalidTxtRecord, LookupOptions, PronounLookup, TxtLookup, TxtResolver, lookup_pronoun_name,
+    lookup_pronouns_for_domain, lookup_txt_records,
+};
+
+pub use parser::{ParserError, SpannedParserError, parse_record, parse_record_spanned};
+pub use pronouns::{
+    BuildError, CommonPronounDef, InvalidRecord, ParsedRecord, PronounDef, PronounForm,
+    PronounRecord, PronounSet, PronounSetBuilder, PronounTag, Provenance, ResolvedForm,
+};
+pub use zone::PronounZone;
 
-pub use parser::{ParserError, parse_record};
-pub use pronouns::{CommonPronounDef, PronounDef, PronounRecord, PronounSet, PronounTag};
+/// The label that pronoun records are published under, see [`resolve_pronouns_for_domain`].
+pub const PRONOUNS_LABEL: &str = "pronouns";
 
+/// Resolves the pronoun records for `domain`, by querying the TXT records of
+/// `pronouns.<domain>`.
+///
+/// The prefix is always prepended, so `pronouns.example` resolves
+/// `pronouns.pronouns.example`. Use [`query_pronoun_name`] to query an exact name.
 #[cfg(feature = "dns_resolve")]
-pub fn resolve_pronouns(domain: &str) -> std::io::Result<Vec<pronouns::PronounRecord>> {
-    // make sure there is a `pronouns.` prefix on the domain
-    let mut domain = domain;
-    let domain_qualified;
+pub fn resolve_pronouns_for_domain(domain: &str) -> std::io::Result<Vec<pronouns::PronounRecord>> {
+    let lookup =
+        lookup_pronouns_for_domain(&SystemResolver::new()?, domain, &LookupOptions::default())?;
 
-    if !domain.starts_with("pronouns.") {
-        domain_qualified = format!("pronouns.{}", domain);
-        domain = &domain_qualified;
-    }
+    Ok(lookup.records)
+}
 
-    let txt_records = query_txt(domain)?;
+/// Queries the TXT records of exactly `name` and parses them as pronoun records.
+#[cfg(feature = "dns_resolve")]
+pub fn query_pronoun_name(name: &str) -> std::io::Result<Vec<pronouns::PronounRecord>> {
+    let lookup = lookup_pronoun_name(&SystemResolver::new()?, name, &LookupOptions::default())?;
 
-    let pronoun_records = parse_records(
-        &txt_records
-            .iter()
-            .map(|s| s.as_str())
-            .collect::<Vec<&str>>(),
-    );
+    Ok(lookup.records)
+}
 
-    Ok(pronoun_records)
+#[cfg(feature = "dns_resolve")]
+#[deprecated(
+    since = "0.1.3",
+    note = "guesses whether `domain` already has the `pronouns.` prefix, use `resolve_pronouns_for_domain` or `query_pronoun_name` instead"
+)]
+pub fn resolve_pronouns(domain: &str) -> std::io::Result<Vec<pronouns::PronounRecord>> {
+    if domain.starts_with("pronouns.") {
+        query_pronoun_name(domain)
+    } else {
+        resolve_pronouns_for_domain(domain)
+    }
 }
 
+/// Parses every record, skipping the invalid ones, sorted by [`order::PreferredFirst`].
+///
+/// Use [`order::sort_records`] to sort them another way, or [`parse_records_with`] to keep the
+/// order they were published in, or to find out why records are invalid.
 pub fn parse_records(records: &[&str]) -> Vec<pronouns::PronounRecord> {
+    let (records, _) = parse_records_with(records, &ParseOptions::default());
+    records.into_iter().map(|parsed| parsed.record).collect()
+}
+
+/// How [`parse_records_with`] parses records.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ParseOptions {
+    /// Keep the records in the order they were given, instead of sorting them by
+    /// [`order::PreferredFirst`]. Either way, [`order::sort_records`] or [`order::sort_parsed`]
+    /// can sort them another way afterwards.
+    pub keep_order: bool,
+}
+
+/// Parses every record like [`parse_records`], keeping the index and raw text of each one.
+///
+/// The records that don't parse are returned separately, in the order they were given.
+pub fn parse_records_with(
+    records: &[&str],
+    options: &ParseOptions,
+) -> (Vec<ParsedRecord>, Vec<InvalidRecord>) {
     let mut pronoun_records = Vec::new();
+    let mut invalid = Vec::new();
 
-    for record in records {
+    for (index, record) in records.iter().enumerate() {
         match parse_record(record) {
-            Ok(pronoun_record) => pronoun_records.push(pronoun_record),
-            Err(e) => {
-                eprintln!("Warning: Failed to parse record '{}': {:?}", record, e);
-            }
+            Ok(pronoun_record) => pronoun_records.push(ParsedRecord {
+                index,
+                raw: record.to_string(),
+                record: pronoun_record,
+            }),
+            Err(error) => invalid.push(InvalidRecord {
+                index,
+                raw: record.to_string(),
+                error,
+            }),
         }
     }
 
-    pronoun_records.sort();
+    if !options.keep_order {
+        // stable, so equal records stay in the order they were published in
+        pronoun_records.sort_by(|a, b| a.record.cmp(&b.record));
+    }
+
+    (pronoun_records, invalid)
+}
 
-    pronoun_records
+/// Rewrites a record into its canonical form, such as `SHE /    HER` into `she/her`.
+///
+/// Pronouns and tags are lowercased, duplicate tags are dropped, and whitespace is normalized.
+/// Records that don't parse are returned unchanged, use [`parse_record`] to check them first.
+pub fn canonicalize(input: &str) -> String {
+    match parse_record(input) {
+        Ok(record) => record.canonical(),
+        Err(_) => input.to_owned(),
+    }
 }
 
-#[cfg(all(test, feature = "dns_resolve"))]
+#[cfg(test)]
 mod tests {
     use super::*;
+    use crate::testing::StaticResolver;
+
+    #[test]
+    fn test_canonicalize() {
+        assert_eq!(canonicalize("SHE /    HER"), "she/her");
+        assert_eq!(canonicalize("he/him;;;preferred"), "he/him; preferred");
+        assert_eq!(
+            canonicalize(" they/them ; PLURAL;plural ;preferred #  Hi there "),
+            "they/them; plural; preferred # Hi there"
+        );
+        assert_eq!(canonicalize("*#any"), "* # any");
+        assert_eq!(canonicalize("she/her/"), "she/her/");
+
+        for record in ["she/her", "he/him; preferred", "# hello", "!"] {
+            assert_eq!(canonicalize(record), record);
+        }
+    }
+
+    #[test]
+    fn test_parse_records_with() {
+        let records = ["they/them", "she/", "she/her; preferred", "  he/HIM "];
+
+        let (sorted, invalid) = parse_records_with(&records, &ParseOptions::default());
+        let indices = sorted.iter().map(|parsed| parsed.index).collect::<Vec<_>>();
+        assert_eq!(indices, vec![2, 3, 0]);
+        assert_eq!(sorted[1].raw, "  he/HIM ");
+        assert_eq!(
+            invalid,
+            vec![InvalidRecord {
+                index: 1,
+                raw: "she/".to_string(),
+                error: ParserError::NotEnoughPronounParts,
+            }]
+        );
+
+        let options = ParseOptions { keep_order: true };
+        let (kept, _) = parse_records_with(&records, &options);
+        let indices = kept.iter().map(|parsed| parsed.index).collect::<Vec<_>>();
+        assert_eq!(indices, vec![0, 2, 3]);
+        assert_eq!(kept[0].record, parse_record("they/them").unwrap());
+    }
 
     #[test]
     fn test_resolve_pronouns() {
-        let result = resolve_pronouns("kinda.red");
+        let resolver = StaticResolver::new()
+            .with_records("pronouns.kinda.red", ["she/her; preferred", "they/them"]);
+        let options = LookupOptions::default();
+
+        let result = lookup_pronouns_for_domain(&resolver, "kinda.red", &options);
         assert!(result.is_ok());
-        let result2 = resolve_pronouns("pronouns.kinda.red");
+        let result2 = lookup_pronoun_name(&resolver, "pronouns.kinda.red", &options);
         assert!(result2.is_ok());
+        assert_eq!(result.unwrap().records, result2.unwrap().records);
+    }
+
+    #[test]
+    fn test_resolve_pronouns_errors() {
+        let resolver = StaticResolver::new()
+            .with_timeout("pronouns.slow.example")
+            .with_server_failure("pronouns.broken.example");
+        let options = LookupOptions::default();
+
+        let err = lookup_pronouns_for_domain(&resolver, "missing.example", &options).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::NotFound);
+
+        let err = lookup_pronouns_for_domain(&resolver, "slow.example", &options).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::TimedOut);
+
+        assert!(lookup_pronouns_for_domain(&resolver, "broken.example", &options).is_err());
     }
 }
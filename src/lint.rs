@@ -0,0 +1,367 @@
+//! Checks pronoun records for mistakes, and suggests how to fix them.
+//!
+//! Besides records that fail to parse, records that parse but are likely not what was meant
+//! are reported, such as several preferred sets or `!` next to other sets.
+
+use std::fmt::Display;
+use std::ops::Range;
+
+use crate::dedup::same_set;
+use crate::lookup::TxtLookup;
+use crate::parser::{ParserError, SpannedParserError, parse_record_spanned};
+use crate::pronouns::{PronounRecord, PronounSet, PronounTag};
+
+/// How many parse errors are repaired in a row before giving up on suggesting a fix.
+const MAX_REPAIRS: usize = 8;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    /// The record is invalid, and is ignored by clients
+    Error,
+    /// The record is valid, but likely not what was meant
+    Warning,
+}
+
+impl Display for Severity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Severity::Error => write!(f, "error"),
+            Severity::Warning => write!(f, "warning"),
+        }
+    }
+}
+
+/// How to fix a [`LintIssue`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Suggestion {
+    /// Replace the record with this one
+    Replace(String),
+    /// Remove the record
+    Remove,
+}
+
+impl Display for Suggestion {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Suggestion::Replace(record) => write!(f, "replace the record with `{}`", record),
+            Suggestion::Remove => write!(f, "remove the record"),
+        }
+    }
+}
+
+/// A problem with one of the records passed to [`lint_records`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LintIssue {
+    pub severity: Severity,
+    /// Index of the record in the records that were linted
+    pub record_index: usize,
+    /// The record, as it was given
+    pub record: String,
+    pub message: String,
+    /// The bytes of `record` the issue is about, if it is about a part of it
+    pub span: Option<Range<usize>>,
+    /// `None` if there is no obvious fix
+    pub suggestion: Option<Suggestion>,
+}
+
+/// Formats the issue like a compiler error, with the record and the span underlined.
+impl Display for LintIssue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "{}: {}", self.severity, self.message)?;
+        writeln!(f, "  --> record {}", self.record_index + 1)?;
+        writeln!(f, "   |")?;
+        writeln!(f, "   | {}", self.record)?;
+
+        if let Some(span) = &self.span {
+            // the record is shown as-is, so count characters rather than bytes
+            let start = self.record[..span.start].chars().count();
+            let len = self.record[span.clone()].chars().count().max(1);
+            writeln!(f, "   | {}{}", " ".repeat(start), "^".repeat(len))?;
+        }
+
+        if let Some(suggestion) = &self.suggestion {
+            writeln!(f, "   = help: {}", suggestion)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Checks every record, returning the issues ordered by record.
+pub fn lint_records(records: &[&str]) -> Vec<LintIssue> {
+    let mut issues = Vec::new();
+    let mut parsed: Vec<(usize, PronounRecord)> = Vec::new();
+
+    for (index, record) in records.iter().enumerate() {
+        let issue = |severity, message: String, span, suggestion| LintIssue {
+            severity,
+            record_index: index,
+            record: record.to_string(),
+            message,
+            span,
+            suggestion,
+        };
+
+        match parse_record_spanned(record) {
+            Ok(pronoun_record) => {
+                let canonical = pronoun_record.canonical();
+                if *record != canonical {
+                    issues.push(issue(
+                        Severity::Warning,
+                        "record is not in canonical form".to_string(),
+                        None,
+                        Some(Suggestion::Replace(canonical)),
+                    ));
+                }
+                parsed.push((index, pronoun_record));
+            }
+            Err(error) => {
+                let suggestion = match error.error {
+                    ParserError::Empty => Some(Suggestion::Remove),
+                    _ => repair(record, &error)
+                        .map(|repaired| Suggestion::Replace(repaired.canonical())),
+                };
+                issues.push(issue(
+                    Severity::Error,
+                    error.error.to_string(),
+                    Some(error.span),
+                    suggestion,
+                ));
+            }
+        }
+    }
+
+    let issue = |index: usize, message: &str, suggestion| LintIssue {
+        severity: Severity::Warning,
+        record_index: index,
+        record: records[index].to_string(),
+        message: message.to_string(),
+        span: None,
+        suggestion: Some(suggestion),
+    };
+
+    let has_other_sets = parsed.iter().any(|(_, record)| {
+        matches!(
+            record.set,
+            Some(PronounSet::Defined { .. } | PronounSet::Any)
+        )
+    });
+
+    let mut seen_preferred = false;
+    for (i, (index, record)) in parsed.iter().enumerate() {
+        let Some(set) = &record.set else {
+            continue;
+        };
+
+        if parsed[..i].iter().any(|(_, earlier)| {
+            earlier
+                .set
+                .as_ref()
+                .is_some_and(|earlier| same_set(earlier, set))
+        }) {
+            issues.push(issue(
+                *index,
+                "the same pronoun set is already defined by an earlier record",
+                Suggestion::Remove,
+            ));
+            continue;
+        }
+
+        match set {
+            PronounSet::Defined { definition, tags } if tags.contains(&PronounTag::Preferred) => {
+                if seen_preferred {
+                    let mut fixed = record.clone();
+                    fixed.set = Some(PronounSet::Defined {
+                        definition: definition.clone(),
+                        tags: tags
+                            .iter()
+                            .filter(|tag| **tag != PronounTag::Preferred)
+                            .cloned()
+                            .collect(),
+                    });
+                    issues.push(issue(
+                        *index,
+                        "only one pronoun set should be preferred",
+                        Suggestion::Replace(fixed.canonical()),
+                    ));
+                }
+                seen_preferred = true;
+            }
+            PronounSet::None if has_other_sets => {
+                issues.push(issue(
+                    *index,
+                    "`!` means no pronouns, but other records define pronoun sets",
+                    Suggestion::Remove,
+                ));
+            }
+            _ => {}
+        }
+    }
+
+    issues.sort_by_key(|issue| issue.record_index);
+    issues
+}
+
+/// Checks the records of a lookup like [`lint_records`], with an error for each record that
+/// isn't valid UTF-8, numbered after the ones that are.
+pub fn lint_lookup(lookup: &TxtLookup) -> Vec<LintIssue> {
+    let records = lookup
+        .records
+        .iter()
+        .map(String::as_str)
+        .collect::<Vec<&str>>();
+    let mut issues = lint_records(&records);
+
+    for (i, invalid) in lookup.invalid.iter().enumerate() {
+        issues.push(LintIssue {
+            severity: Severity::Error,
+            record_index: records.len() + i,
+            record: invalid.lossy().into_owned(),
+            message: "record is not valid UTF-8".to_string(),
+            span: None,
+            suggestion: None,
+        });
+    }
+
+    issues
+}
+
+/// Tries to fix the errors in `record` one at a time, by removing the part that is in the way.
+fn repair(record: &str, error: &SpannedParserError) -> Option<PronounRecord> {
+    let mut record = record.to_owned();
+    let mut error = error.clone();
+
+    for _ in 0..MAX_REPAIRS {
+        let span = error.span.clone();
+        let removed = match error.error {
+            // the extra part and the `/` before it
+            ParserError::TooManyPronounParts => record[..span.start].rfind('/')?..span.end,
+            // the tag and the `;` before it
+            ParserError::InvalidTag => record[..span.start].rfind(';')?..span.end,
+            ParserError::TrailingSlash
+            | ParserError::TagsNotAllowed
+            | ParserError::InvalidFormat
+            | ParserError::TrailingCharacters
+                if !span.is_empty() =>
+            {
+                span
+            }
+            _ => return None,
+        };
+        record.replace_range(removed, "");
+
+        match parse_record_spanned(&record) {
+            Ok(repaired) => return Some(repaired),
+            Err(next) => error = next,
+        }
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn suggestions(records: &[&str]) -> Vec<(usize, Severity, Option<Suggestion>)> {
+        lint_records(records)
+            .into_iter()
+            .map(|issue| (issue.record_index, issue.severity, issue.suggestion))
+            .collect()
+    }
+
+    fn replace(record: &str) -> Option<Suggestion> {
+        Some(Suggestion::Replace(record.to_string()))
+    }
+
+    #[test]
+    fn test_invalid_utf8() {
+        let lookup = TxtLookup {
+            name: "pronouns.example.org".to_string(),
+            records: vec!["she/her".to_string(), "she/her/".to_string()],
+            authenticated: false,
+            ttl: None,
+            invalid: vec![crate::InvalidTxtRecord {
+                data: b"he/h\xffm".to_vec(),
+            }],
+        };
+
+        let issues = lint_lookup(&lookup);
+        assert_eq!(issues.len(), 2);
+        assert_eq!(issues[1].record_index, 2);
+        assert_eq!(issues[1].record, "he/h\u{fffd}m");
+        assert_eq!(issues[1].severity, Severity::Error);
+    }
+
+    #[test]
+    fn test_clean_records() {
+        assert_eq!(
+            lint_records(&["she/her; preferred", "they/them # also fine", "*"]),
+            vec![]
+        );
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert_eq!(
+            suggestions(&["she/her/", "he/him; favourite", "*; preferred # any", ""]),
+            vec![
+                (0, Severity::Error, replace("she/her")),
+                (1, Severity::Error, replace("he/him")),
+                (2, Severity::Error, replace("* # any")),
+                (3, Severity::Error, Some(Suggestion::Remove)),
+            ]
+        );
+        assert_eq!(
+            suggestions(&["he/him/his/his/himself/extra/more"]),
+            vec![(0, Severity::Error, replace("he/him/his/his/himself"))]
+        );
+        assert_eq!(suggestions(&["he"]), vec![(0, Severity::Error, None)]);
+    }
+
+    #[test]
+    fn test_non_canonical() {
+        assert_eq!(
+            suggestions(&["SHE /    HER", "he/him;;;preferred"]),
+            vec![
+                (0, Severity::Warning, replace("she/her")),
+                (1, Severity::Warning, replace("he/him; preferred")),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_semantic_issues() {
+        assert_eq!(
+            suggestions(&["she/her; preferred", "they/them; preferred", "she/her", "!",]),
+            vec![
+                (1, Severity::Warning, replace("they/them")),
+                (2, Severity::Warning, Some(Suggestion::Remove)),
+                (3, Severity::Warning, Some(Suggestion::Remove)),
+            ]
+        );
+
+        // on its own, `!` is fine
+        assert_eq!(lint_records(&["! # no pronouns please"]), vec![]);
+    }
+
+    #[test]
+    fn test_duplicate_spellings() {
+        // the short and full spellings of a set are the same set, like `dedup_records` finds
+        assert_eq!(
+            suggestions(&["she/her", "she/her/her/hers/herself"]),
+            vec![(1, Severity::Warning, Some(Suggestion::Remove))]
+        );
+        // but not in another language
+        assert_eq!(suggestions(&["sie/sie", "sie/sie; lang=de"]), vec![]);
+    }
+
+    #[test]
+    fn test_render() {
+        let issues = lint_records(&["she/her; favourite"]);
+        assert_eq!(
+            issues[0].to_string(),
+            "error: unknown tag\n  --> record 1\n   |\n   | she/her; favourite\n   |          ^^^^^^^^^\n   = help: replace the record with `she/her`\n"
+        );
+    }
+}
@@ -0,0 +1,184 @@
+use std::borrow::Cow;
+use std::fmt::Display;
+use std::io;
+
+use crate::{PRONOUNS_LABEL, ParseOptions, parse_records_with, pronouns::PronounRecord};
+
+/// Something that can look up TXT records, such as [`SystemResolver`](crate::SystemResolver).
+///
+/// Implement this to bring your own resolver, and use it with [`lookup_pronouns_for_domain`]
+/// and [`lookup_pronoun_name`].
+pub trait TxtResolver {
+    fn lookup_txt(&self, name: &str) -> io::Result<TxtLookup>;
+}
+
+impl<R: TxtResolver + ?Sized> TxtResolver for Box<R> {
+    fn lookup_txt(&self, name: &str) -> io::Result<TxtLookup> {
+        (**self).lookup_txt(name)
+    }
+}
+
+/// The TXT records found at a name.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TxtLookup {
+    pub name: String,
+    /// Every TXT record, with its character-strings joined together
+    pub records: Vec<String>,
+    /// Whether the records were validated with DNSSEC
+    pub authenticated: bool,
+    /// How many seconds the records may be cached for, if known
+    pub ttl: Option<u32>,
+    /// The TXT records that aren't text, which are left out of `records`
+    pub invalid: Vec<InvalidTxtRecord>,
+}
+
+/// A TXT record that isn't valid UTF-8, so it can't be a pronoun record.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InvalidTxtRecord {
+    /// The record, with its character-strings joined together
+    pub data: Vec<u8>,
+}
+
+impl InvalidTxtRecord {
+    /// The record, with the invalid bytes replaced by `�`.
+    pub fn lossy(&self) -> Cow<'_, str> {
+        String::from_utf8_lossy(&self.data)
+    }
+}
+
+impl Display for InvalidTxtRecord {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "TXT record is not valid UTF-8: {:?}", self.lossy())
+    }
+}
+
+/// The pronoun records found at a name.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PronounLookup {
+    pub name: String,
+    pub records: Vec<PronounRecord>,
+    /// Whether the records were validated with DNSSEC
+    pub authenticated: bool,
+    /// How many seconds the records may be cached for, if known
+    pub ttl: Option<u32>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LookupOptions {
+    /// Fail the lookup if the records could not be validated with DNSSEC
+    pub require_dnssec: bool,
+    /// Keep the records in the order they were published in, instead of sorting them
+    pub keep_order: bool,
+}
+
+/// Looks up the pronoun records of `domain` at `pronouns.<domain>`.
+pub fn lookup_pronouns_for_domain<R: TxtResolver + ?Sized>(
+    resolver: &R,
+    domain: &str,
+    options: &LookupOptions,
+) -> io::Result<PronounLookup> {
+    lookup_pronoun_name(resolver, &format!("{}.{}", PRONOUNS_LABEL, domain), options)
+}
+
+/// Looks up the pronoun records at exactly `name`.
+pub fn lookup_pronoun_name<R: TxtResolver + ?Sized>(
+    resolver: &R,
+    name: &str,
+    options: &LookupOptions,
+) -> io::Result<PronounLookup> {
+    let txt = lookup_txt_records(resolver, name, options)?;
+
+    let parse_options = ParseOptions {
+        keep_order: options.keep_order,
+    };
+    // records that don't parse are ignored, like clients do
+    let (records, _) = parse_records_with(
+        &txt.records
+            .iter()
+            .map(|s| s.as_str())
+            .collect::<Vec<&str>>(),
+        &parse_options,
+    );
+    let records = records.into_iter().map(|parsed| parsed.record).collect();
+
+    Ok(PronounLookup {
+        name: txt.name,
+        records,
+        authenticated: txt.authenticated,
+        ttl: txt.ttl,
+    })
+}
+
+/// Looks up the TXT records at exactly `name`, checking them against `options` like
+/// [`lookup_pronoun_name`] does, but without parsing them.
+pub fn lookup_txt_records<R: TxtResolver + ?Sized>(
+    resolver: &R,
+    name: &str,
+    options: &LookupOptions,
+) -> io::Result<TxtLookup> {
+    let txt = resolver.lookup_txt(name)?;
+
+    if options.require_dnssec && !txt.authenticated {
+        return Err(io::Error::other(format!(
+            "records of {} are not authenticated with DNSSEC",
+            txt.name
+        )));
+    }
+
+    Ok(txt)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::StaticResolver;
+
+    #[test]
+    fn test_lookup_prepends_label() {
+        let resolver = StaticResolver::new().with_records("pronouns.pronouns.example", ["she/her"]);
+
+        let lookup =
+            lookup_pronouns_for_domain(&resolver, "pronouns.example", &LookupOptions::default())
+                .unwrap();
+        assert_eq!(lookup.name, "pronouns.pronouns.example");
+        assert_eq!(lookup.records.len(), 1);
+        assert!(!lookup.authenticated);
+    }
+
+    #[test]
+    fn test_require_dnssec() {
+        let options = LookupOptions {
+            require_dnssec: true,
+            ..Default::default()
+        };
+
+        let unsigned = StaticResolver::new().with_records("pronouns.example", ["she/her"]);
+        assert!(lookup_pronoun_name(&unsigned, "pronouns.example", &options).is_err());
+
+        let signed =
+            StaticResolver::new().with_authenticated_records("pronouns.example", ["she/her"]);
+        let lookup = lookup_pronoun_name(&signed, "pronouns.example", &options).unwrap();
+        assert!(lookup.authenticated);
+    }
+
+    #[test]
+    fn test_keep_order() {
+        let resolver =
+            StaticResolver::new().with_records("pronouns.example", ["they/them", "she/her"]);
+        let subjects = |options: &LookupOptions| {
+            lookup_pronoun_name(&resolver, "pronouns.example", options)
+                .unwrap()
+                .records
+                .iter()
+                .map(|record| record.to_string())
+                .collect::<Vec<_>>()
+        };
+
+        assert!(subjects(&LookupOptions::default())[0].starts_with("she/her"));
+        let options = LookupOptions {
+            keep_order: true,
+            ..Default::default()
+        };
+        assert!(subjects(&options)[0].starts_with("they/them"));
+    }
+}
@@ -1,58 +1,1235 @@
 use std::{
-    fmt::Debug,
+    fmt::Display,
     io::{self, BufRead},
+    process::ExitCode,
 };
 
+use podns::TxtResolver;
+use podns::human::HumanDisplay;
+use podns::language;
+use podns::output::{DomainReport, OutputFormat};
+
+const HELP: &str = "\
+Look up and check pronoun records published over DNS
+
+Usage: podns [resolve] [OPTIONS] [DOMAIN]...
+       podns <COMMAND> [OPTIONS]
+
+Commands:
+  resolve     Look up the pronouns of domains (the default)
+  parse       Parse records without looking anything up
+  lint        Check records for mistakes, and suggest fixes
+  fmt         Rewrite records into canonical form
+  generate    Print the DNS records to publish pronouns with
+  serve-dns   Serve pronoun records over DNS
+  serve-http  Serve a JSON API for looking up pronouns
+
+Options:
+  -h, --help     Print help, or the help of a command with `podns <COMMAND> --help`
+  -V, --version  Print the version
+
+Exit codes:
+  0  success
+  1  any other error, such as failing to read a file
+  2  invalid arguments
+  3  no pronoun records were found
+  4  a lookup failed, such as on a timeout
+  5  some records are invalid
+";
+
+const RESOLVE_HELP: &str = "\
+Look up the pronouns of domains, at pronouns.<domain>
+
+Usage: podns [resolve] [OPTIONS] [DOMAIN]...
+
+Without a domain, asks for one if stdin is a terminal.
+
+Options:
+      --raw-name          Query each name as given, without prepending `pronouns.`
+      --require-dnssec    Fail unless the records are validated with DNSSEC
+      --format <FORMAT>   text, json, ndjson or csv [default: text]
+      --examples          Show example sentences for each set of pronouns
+      --doh <URL>         Use DNS-over-HTTPS (with the doh feature)
+      --doh-json <URL>    Use the JSON flavour of DNS-over-HTTPS (with the doh feature)
+      --dot <ADDR>        Use DNS-over-TLS (with the dot feature)
+      --tls-name <NAME>   The name on the certificate of the --dot server
+  -n, --non-interactive   Never ask for a domain on stdin
+  -h, --help              Print help
+";
+
+const PARSE_HELP: &str = "\
+Parse records without looking anything up
+
+Usage: podns parse [RECORD]...
+
+Pass `-` to read one record per line from stdin.
+
+Options:
+      --file <FILE>  Read one record per line from a file
+  -h, --help         Print help
+";
+
+const LINT_HELP: &str = "\
+Check records for mistakes, and suggest fixes
+
+Usage: podns lint [OPTIONS] [DOMAIN]...
+
+Options:
+      --stdin             Check the records on stdin, one per line
+      --raw-name          Query each name as given, without prepending `pronouns.`
+      --doh <URL>         Use DNS-over-HTTPS (with the doh feature)
+      --doh-json <URL>    Use the JSON flavour of DNS-over-HTTPS (with the doh feature)
+      --dot <ADDR>        Use DNS-over-TLS (with the dot feature)
+      --tls-name <NAME>   The name on the certificate of the --dot server
+  -h, --help              Print help
+";
+
+const FMT_HELP: &str = "\
+Rewrite records into canonical form
+
+Usage: podns fmt [OPTIONS] [RECORD]...
+
+Options:
+      --stdin             Read the records from stdin, one per line
+      --domain <DOMAIN>   Format the records published for a domain
+      --diff              Only print the records that would change
+      --doh <URL>         Use DNS-over-HTTPS for --domain (with the doh feature)
+      --doh-json <URL>    Use the JSON flavour of DNS-over-HTTPS (with the doh feature)
+      --dot <ADDR>        Use DNS-over-TLS for --domain (with the dot feature)
+      --tls-name <NAME>   The name on the certificate of the --dot server
+  -h, --help              Print help
+";
+
+const GENERATE_HELP: &str = "\
+Print the DNS records to publish pronouns with
+
+Usage: podns generate --domain <DOMAIN> [OPTIONS] <RECORD>...
+       podns generate --config <FILE> [OPTIONS]
+
+Options:
+      --domain <DOMAIN>  The domain to publish the records for
+      --config <FILE>    Read the records of several domains from a TOML file (with the config feature)
+      --ttl <SECONDS>    How long resolvers may cache the records
+      --target <FORMAT>  bind, cloudflare, route53 or terraform [default: bind]
+  -h, --help             Print help
+";
+
+const SERVE_DNS_HELP: &str = "\
+Serve pronoun records over DNS, reloading the config on SIGHUP
+
+Usage: podns serve-dns --config <FILE> [OPTIONS]
+
+Options:
+      --config <FILE>  The TOML file with the records to serve
+      --listen <ADDR>  [default: 0.0.0.0:53]
+  -h, --help           Print help
+";
+
+const SERVE_HTTP_HELP: &str = "\
+Serve a JSON API for looking up pronouns, at /v1/pronouns/{domain}
+
+Usage: podns serve-http [OPTIONS]
+
+Options:
+      --listen <ADDR>     [default: 127.0.0.1:8080]
+      --doh <URL>         Use DNS-over-HTTPS (with the doh feature)
+      --doh-json <URL>    Use the JSON flavour of DNS-over-HTTPS (with the doh feature)
+      --dot <ADDR>        Use DNS-over-TLS (with the dot feature)
+      --tls-name <NAME>   The name on the certificate of the --dot server
+  -h, --help              Print help
+";
+
+#[derive(Debug)]
 enum CliError {
+    /// The arguments don't make sense, the usage is printed with the error
+    Usage(String),
+    /// The lookups worked, but found no valid records
+    NoRecords(String),
+    LookupFailed(String),
+    InvalidRecords(String),
     IoError(io::Error),
-    Other(String),
 }
 
-impl Debug for CliError {
+impl CliError {
+    fn exit_code(&self) -> u8 {
+        match self {
+            CliError::IoError(_) => 1,
+            CliError::Usage(_) => 2,
+            CliError::NoRecords(_) => 3,
+            CliError::LookupFailed(_) => 4,
+            CliError::InvalidRecords(_) => 5,
+        }
+    }
+}
+
+impl Display for CliError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            CliError::IoError(e) => write!(f, "I/O Error - {}", e),
-            CliError::Other(msg) => write!(f, "{}", msg),
+            CliError::Usage(msg) => write!(f, "{}\n\nFor more information, try --help", msg),
+            CliError::IoError(e) => write!(f, "I/O error: {}", e),
+            CliError::NoRecords(msg) | CliError::LookupFailed(msg) => write!(f, "{}", msg),
+            CliError::InvalidRecords(msg) => write!(f, "{}", msg),
         }
     }
 }
 
-fn main() -> Result<(), CliError> {
-    // read from args, or fall back to stdin
-    let sysargs = std::env::args().collect::<Vec<String>>();
+/// An argument of a command line, as returned by [`ArgParser::next`].
+#[derive(Debug, PartialEq, Eq)]
+enum Arg {
+    /// `--name` or `-n`, whose value is read with [`ArgParser::value`]
+    Option(String),
+    Positional(String),
+}
+
+/// Splits a command line into options and positional arguments.
+///
+/// Options take their value either from the next argument or after a `=`, as in
+/// `--format=json`. Everything after `--` is positional, and so is `-` on its own.
+struct ArgParser<I> {
+    args: I,
+    // the value after the `=` of the last option, until it's taken
+    inline_value: Option<String>,
+    last_option: Option<String>,
+    only_positional: bool,
+}
+
+impl<I: Iterator<Item = String>> ArgParser<I> {
+    fn new(args: I) -> Self {
+        ArgParser {
+            args,
+            inline_value: None,
+            last_option: None,
+            only_positional: false,
+        }
+    }
+
+    fn next(&mut self) -> Result<Option<Arg>, CliError> {
+        if let (Some(option), Some(_)) = (&self.last_option, &self.inline_value) {
+            return Err(CliError::Usage(format!("{} doesn't take a value", option)));
+        }
+
+        let Some(arg) = self.args.next() else {
+            return Ok(None);
+        };
+
+        if self.only_positional || arg == "-" || !arg.starts_with('-') {
+            return Ok(Some(Arg::Positional(arg)));
+        }
+        if arg == "--" {
+            self.only_positional = true;
+            return self.next();
+        }
+
+        let option = match arg.split_once('=') {
+            Some((option, value)) if arg.starts_with("--") => {
+                self.inline_value = Some(value.to_owned());
+                option.to_owned()
+            }
+            _ => arg,
+        };
+        self.last_option = Some(option.clone());
+        Ok(Some(Arg::Option(option)))
+    }
+
+    /// The value of the last option returned by [`ArgParser::next`].
+    fn value(&mut self, what: &str) -> Result<String, CliError> {
+        let option = self.last_option.clone().unwrap_or_default();
+
+        self.inline_value
+            .take()
+            .or_else(|| self.args.next())
+            .ok_or_else(|| CliError::Usage(format!("{} requires {}", option, what)))
+    }
+}
+
+fn unknown_option(option: &str) -> CliError {
+    CliError::Usage(format!("Unknown option: {}", option))
+}
+
+fn main() -> ExitCode {
+    match run(std::env::args().skip(1)) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            ExitCode::from(e.exit_code())
+        }
+    }
+}
+
+fn run(mut args: impl Iterator<Item = String>) -> Result<(), CliError> {
+    let mut args = args.by_ref().peekable();
+
+    let command = match args.peek().map(String::as_str) {
+        Some(
+            command @ ("resolve" | "parse" | "lint" | "fmt" | "generate" | "serve-dns"
+            | "serve-http"),
+        ) => {
+            let command = command.to_owned();
+            args.next();
+            command
+        }
+        Some("help") => {
+            args.next();
+            print!(
+                "{}",
+                command_help(args.next().as_deref().unwrap_or_default())
+            );
+            return Ok(());
+        }
+        Some("--version" | "-V") => {
+            println!("podns {}", env!("CARGO_PKG_VERSION"));
+            return Ok(());
+        }
+        Some("--help" | "-h") => {
+            print!("{}", HELP);
+            return Ok(());
+        }
+        // looking up domains is the default, like in older versions
+        _ => "resolve".to_owned(),
+    };
+
+    let mut args = ArgParser::new(args);
+    match command.as_str() {
+        "resolve" => resolve(&mut args),
+        "parse" => parse(&mut args),
+        "lint" => lint(&mut args),
+        "fmt" => fmt(&mut args),
+        "generate" => generate(&mut args),
+        #[cfg(feature = "dns_server")]
+        "serve-dns" => serve_dns(&mut args),
+        #[cfg(not(feature = "dns_server"))]
+        "serve-dns" => Err(CliError::Usage(
+            "serve-dns requires podns to be built with the dns_server feature".into(),
+        )),
+        #[cfg(feature = "http_server")]
+        "serve-http" => serve_http(&mut args),
+        #[cfg(not(feature = "http_server"))]
+        "serve-http" => Err(CliError::Usage(
+            "serve-http requires podns to be built with the http_server feature".into(),
+        )),
+        _ => unreachable!("unknown command {}", command),
+    }
+}
+
+fn command_help(command: &str) -> &'static str {
+    match command {
+        "resolve" => RESOLVE_HELP,
+        "parse" => PARSE_HELP,
+        "lint" => LINT_HELP,
+        "fmt" => FMT_HELP,
+        "generate" => GENERATE_HELP,
+        "serve-dns" => SERVE_DNS_HELP,
+        "serve-http" => SERVE_HTTP_HELP,
+        _ => HELP,
+    }
+}
+
+/// Looks up the pronouns of the given domains, or asks for one.
+fn resolve(args: &mut ArgParser<impl Iterator<Item = String>>) -> Result<(), CliError> {
+    let mut raw_name = false;
+    let mut options = podns::LookupOptions::default();
+    let mut format = OutputFormat::default();
+    let mut examples = false;
+    let mut interactive = io::IsTerminal::is_terminal(&io::stdin());
+    let mut resolver_args = ResolverArgs::default();
+    let mut domains = Vec::new();
+
+    while let Some(arg) = args.next()? {
+        let option = match arg {
+            Arg::Positional(domain) => {
+                domains.push(domain);
+                continue;
+            }
+            Arg::Option(option) => option,
+        };
+
+        match option.as_str() {
+            "--help" | "-h" => {
+                print!("{}", RESOLVE_HELP);
+                return Ok(());
+            }
+            // query the given name as-is, without prepending `pronouns.`
+            "--raw-name" => raw_name = true,
+            // fail unless the records are validated with DNSSEC
+            "--require-dnssec" => options.require_dnssec = true,
+            // text, or a format for other programs to read
+            "--format" => {
+                format = args.value("a format")?.parse().map_err(CliError::Usage)?;
+            }
+            // show how to use the pronouns in sentences
+            "--examples" => examples = true,
+            // never block on stdin, such as in scripts
+            "--non-interactive" | "-n" => interactive = false,
+            _ => resolver_args.parse(&option, args)?,
+        }
+    }
+
+    // before asking for a domain, so a missing resolver is reported straight away
+    let resolver = resolver_args.resolver()?;
+
+    if examples && format != OutputFormat::Text {
+        return Err(CliError::Usage(
+            "--examples only works with --format text".into(),
+        ));
+    }
+
+    if domains.is_empty() {
+        if !interactive {
+            return Err(CliError::Usage("No domain provided".into()));
+        }
 
-    let domain = if sysargs.len() > 1 {
-        sysargs[1].to_owned()
-    } else {
         print!("Enter domain to resolve pronouns for (e.g. kinda.red): ");
         io::Write::flush(&mut io::stdout()).map_err(CliError::IoError)?;
 
-        // read from stdin
-        let stdin = io::stdin();
-        let mut handle = stdin.lock();
         let mut line = String::new();
-        handle.read_line(&mut line).map_err(CliError::IoError)?;
-        line.trim().to_owned()
+        io::stdin()
+            .lock()
+            .read_line(&mut line)
+            .map_err(CliError::IoError)?;
+        domains.push(line.trim().to_owned());
+    }
+
+    if domains.iter().any(|domain| domain.is_empty()) {
+        return Err(CliError::Usage("No domain provided".to_string()));
+    }
+
+    // whether each lookup failed for another reason than the name not existing
+    let mut lookup_failed = Vec::new();
+    let reports = domains
+        .iter()
+        .map(|domain| {
+            let name = if raw_name {
+                domain.clone()
+            } else {
+                format!("{}.{}", podns::PRONOUNS_LABEL, domain)
+            };
+
+            let result = podns::lookup_txt_records(&*resolver, &name, &options);
+
+            lookup_failed.push(matches!(&result, Err(e) if e.kind() != io::ErrorKind::NotFound));
+            match result {
+                Ok(txt) => DomainReport::from_lookup(domain, txt),
+                Err(e) => DomainReport::from_error(domain, &name, &e),
+            }
+        })
+        .collect::<Vec<DomainReport>>();
+
+    let failure = |msg: String| {
+        if lookup_failed.contains(&true) {
+            CliError::LookupFailed(msg)
+        } else {
+            CliError::NoRecords(msg)
+        }
     };
 
-    if domain.is_empty() {
-        return Err(CliError::Other("No domain provided".to_string()));
+    if format == OutputFormat::Text {
+        for report in &reports {
+            for record in &report.records {
+                if let Err(e) = &record.parsed {
+                    eprintln!("Warning: Failed to parse record '{}': {}", record.raw, e);
+                }
+            }
+            for invalid in &report.invalid {
+                eprintln!("Warning: {}", invalid);
+            }
+        }
+
+        // a single failed lookup is only reported as the error
+        if let [report] = reports.as_slice() {
+            if let Some(error) = &report.error {
+                return Err(failure(error.clone()));
+            }
+            if report.valid_records().is_empty() {
+                return Err(failure("No valid pronoun records found".to_string()));
+            }
+        }
     }
 
-    match podns::resolve_pronouns(domain.as_str()) {
-        Ok(mut records) => {
-            if records.is_empty() {
-                return Err(CliError::Other(
-                    "No valid pronoun records found".to_string(),
-                ));
+    if format == OutputFormat::Text {
+        print!("{}", podns::output::format_text(&reports, examples));
+    } else {
+        print!("{}", podns::output::format_reports(&reports, format));
+    }
+
+    // keep going when a lookup fails, and report how many did at the end
+    let failed = reports
+        .iter()
+        .filter(|report| report.error.is_some() || report.valid_records().is_empty())
+        .count();
+    if failed > 0 {
+        return Err(failure(format!(
+            "{} of {} lookups failed",
+            failed,
+            reports.len()
+        )));
+    }
+
+    Ok(())
+}
+
+/// The options that pick how records are looked up, shared by every command that looks them
+/// up. Without any, the system resolver is used.
+#[derive(Default)]
+struct ResolverArgs {
+    #[cfg(feature = "doh")]
+    doh: Option<(String, podns::DohFormat)>,
+    #[cfg(feature = "dot")]
+    dot_server: Option<std::net::SocketAddr>,
+    #[cfg(feature = "dot")]
+    tls_name: Option<String>,
+}
+
+impl ResolverArgs {
+    /// Takes `option` if it is one of the resolver options, otherwise it is unknown.
+    #[cfg_attr(not(any(feature = "doh", feature = "dot")), allow(unused_variables))]
+    fn parse(
+        &mut self,
+        option: &str,
+        args: &mut ArgParser<impl Iterator<Item = String>>,
+    ) -> Result<(), CliError> {
+        match option {
+            // look up the records with DNS-over-HTTPS instead of the system resolver
+            #[cfg(feature = "doh")]
+            "--doh" | "--doh-json" => {
+                let format = if option == "--doh-json" {
+                    podns::DohFormat::Json
+                } else {
+                    podns::DohFormat::Wire
+                };
+                self.doh = Some((args.value("a URL")?, format));
+                Ok(())
             }
+            // look up the records with DNS-over-TLS instead of the system resolver
+            #[cfg(feature = "dot")]
+            "--dot" => {
+                self.dot_server = Some(parse_dot_server(&args.value("a server address")?)?);
+                Ok(())
+            }
+            #[cfg(feature = "dot")]
+            "--tls-name" => {
+                self.tls_name = Some(args.value("a name")?);
+                Ok(())
+            }
+            _ => Err(unknown_option(option)),
+        }
+    }
+
+    /// The resolver picked by the options.
+    fn resolver(self) -> Result<Box<dyn TxtResolver + Send + Sync>, CliError> {
+        #[cfg(feature = "doh")]
+        if let Some((url, format)) = self.doh {
+            return Ok(Box::new(podns::DohResolver::new(url).with_format(format)));
+        }
+
+        #[cfg(feature = "dot")]
+        if let Some(server) = self.dot_server {
+            let name = self.tls_name.ok_or_else(|| {
+                CliError::Usage("--dot requires the name of the server in --tls-name".into())
+            })?;
+            return Ok(Box::new(
+                podns::DotResolver::new(server, &name).map_err(CliError::IoError)?,
+            ));
+        }
 
-            for record in records {
-                println!("{}", record);
+        #[cfg(feature = "dns_resolve")]
+        return Ok(Box::new(
+            podns::SystemResolver::new().map_err(CliError::IoError)?,
+        ));
+
+        #[cfg(not(feature = "dns_resolve"))]
+        Err(no_resolver())
+    }
+}
+
+/// Without the system resolver, records can only be looked up with `--doh` or `--dot`.
+#[cfg(not(feature = "dns_resolve"))]
+fn no_resolver() -> CliError {
+    if cfg!(any(feature = "doh", feature = "dot")) {
+        CliError::Usage(
+            "podns is built without the dns_resolve feature, pick a resolver with --doh or --dot"
+                .into(),
+        )
+    } else {
+        CliError::Usage(
+            "looking up records requires podns to be built with the dns_resolve, doh or dot feature"
+                .into(),
+        )
+    }
+}
+
+/// Parses `<ip>` or `<ip>:<port>`, using the DNS-over-TLS port if none is given.
+#[cfg(feature = "dot")]
+fn parse_dot_server(server: &str) -> Result<std::net::SocketAddr, CliError> {
+    if let Ok(addr) = server.parse::<std::net::SocketAddr>() {
+        return Ok(addr);
+    }
+
+    server
+        .parse::<std::net::IpAddr>()
+        .map(|ip| std::net::SocketAddr::new(ip, podns::DOT_PORT))
+        .map_err(|_| CliError::Usage(format!("Invalid server address: {}", server)))
+}
+
+/// Parses the given records, printing what each one means or why it is invalid.
+fn parse(args: &mut ArgParser<impl Iterator<Item = String>>) -> Result<(), CliError> {
+    let mut records = Vec::new();
+
+    while let Some(arg) = args.next()? {
+        let option = match arg {
+            // one record per line of stdin
+            Arg::Positional(stdin) if stdin == "-" => {
+                records.extend(read_stdin_records()?.into_iter().map(|(_, record)| record));
+                continue;
+            }
+            Arg::Positional(record) => {
+                records.push(record);
+                continue;
+            }
+            Arg::Option(option) => option,
+        };
+
+        match option.as_str() {
+            "--help" | "-h" => {
+                print!("{}", PARSE_HELP);
+                return Ok(());
             }
+            "--file" => {
+                let path = args.value("a path")?;
+                let contents = std::fs::read_to_string(&path).map_err(|e| {
+                    CliError::IoError(io::Error::new(e.kind(), format!("{}: {}", path, e)))
+                })?;
+                let lines = read_records(contents.as_bytes()).map_err(CliError::IoError)?;
+                records.extend(lines.into_iter().map(|(_, record)| record));
+            }
+            _ => return Err(unknown_option(&option)),
+        }
+    }
+
+    if records.is_empty() {
+        return Err(CliError::Usage(
+            "parse requires records, `-` or --file".into(),
+        ));
+    }
 
-            Ok(())
+    let mut invalid = 0;
+    for (i, record) in records.iter().enumerate() {
+        if i > 0 {
+            println!();
         }
-        Err(e) => Err(CliError::IoError(e)),
+        println!("{}", record);
+
+        match podns::parse_record_spanned(record) {
+            Ok(parsed) => print!("{}", describe_record(&parsed)),
+            Err(e) => {
+                // underline where the error is, like `podns lint` does
+                let start = record[..e.span.start].chars().count();
+                let len = record[e.span.clone()].chars().count().max(1);
+                println!("{}{}", " ".repeat(start), "^".repeat(len));
+                println!("  error: {}", e.error);
+                invalid += 1;
+            }
+        }
+    }
+
+    if invalid > 0 {
+        return Err(CliError::InvalidRecords(format!(
+            "{} of {} records are invalid",
+            invalid,
+            records.len()
+        )));
+    }
+
+    Ok(())
+}
+
+/// Lists the forms, tags and comment of a record, one per line.
+fn describe_record(record: &podns::PronounRecord) -> String {
+    let mut lines = Vec::new();
+
+    match &record.set {
+        Some(set @ podns::PronounSet::Defined { definition, tags }) => {
+            let language = set
+                .language()
+                .map_or(Some(&language::ENGLISH), language::language);
+            // the guesser only knows English sets
+            let guesses = if language == Some(&language::ENGLISH) {
+                podns::guess::guess_missing_forms(definition)
+            } else {
+                Vec::new()
+            };
+            // the parts are named after the slots of the set's language, if it is known
+            let forms = language::resolve_forms(set.language(), definition);
+
+            for ((slot, resolved), form) in forms.into_iter().zip(podns::PronounForm::ALL) {
+                // forms that weren't given are inferred from the common set they match
+                let value = match (resolved.value, resolved.provenance) {
+                    (Some(value), podns::Provenance::Inferred(common)) => {
+                        format!("{} (from {}/{})", value, common.subject(), common.object())
+                    }
+                    (Some(value), podns::Provenance::InferredFromLanguage(language)) => {
+                        format!("{} (common in {})", value, language.name)
+                    }
+                    (Some(value), _) => value.to_owned(),
+                    // guesses are only suggestions, so they are never shown as the form
+                    (None, _) => match guesses.iter().find(|guess| guess.form == form) {
+                        Some(guess) => format!(
+                            "- (maybe {}, {:.0}% sure)",
+                            guess.value,
+                            guess.confidence * 100.0
+                        ),
+                        None => "-".to_owned(),
+                    },
+                };
+
+                let label = format!("{}:", slot.replace('_', " "));
+                lines.push(format!("{:<22} {}", label, value));
+            }
+            if !tags.is_empty() {
+                let tags = tags.iter().map(|tag| tag.to_string()).collect::<Vec<_>>();
+                lines.push(format!("tags:                  {}", tags.join(", ")));
+            }
+        }
+        Some(set) => lines.push(set.human("en").to_string()),
+        None => {}
+    }
+
+    if let Some(comment) = &record.comment {
+        lines.push(format!("comment:               {}", comment));
+    }
+
+    lines.iter().map(|line| format!("  {}\n", line)).collect()
+}
+
+/// Reads one record per line of stdin, skipping blank lines.
+fn read_stdin_records() -> Result<Vec<(usize, String)>, CliError> {
+    read_records(io::stdin().lock()).map_err(CliError::IoError)
+}
+
+/// One record per line with its line number, counting from 1, skipping blank lines.
+fn read_records(reader: impl BufRead) -> io::Result<Vec<(usize, String)>> {
+    reader
+        .lines()
+        .enumerate()
+        .map(|(i, line)| line.map(|line| (i + 1, line)))
+        .filter(|line| !matches!(line, Ok((_, line)) if line.trim().is_empty()))
+        .collect()
+}
+
+/// Lints records read with [`read_records`], with the issues numbered by the line of their
+/// record rather than its index, so they can be found in the input.
+fn lint_lines(lines: &[(usize, String)]) -> Vec<podns::lint::LintIssue> {
+    let records = lines
+        .iter()
+        .map(|(_, record)| record.as_str())
+        .collect::<Vec<&str>>();
+
+    let mut issues = podns::lint::lint_records(&records);
+    for issue in &mut issues {
+        issue.record_index = lines[issue.record_index].0 - 1;
+    }
+    issues
+}
+
+/// The TXT records at `pronouns.<domain>`, or at `domain` itself with `raw_name`.
+fn lookup_records(
+    resolver: &dyn TxtResolver,
+    domain: &str,
+    raw_name: bool,
+) -> Result<(String, podns::TxtLookup), CliError> {
+    let name = if raw_name {
+        domain.to_owned()
+    } else {
+        format!("{}.{}", podns::PRONOUNS_LABEL, domain)
+    };
+
+    match resolver.lookup_txt(&name) {
+        Ok(lookup) => Ok((name, lookup)),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {
+            Err(CliError::NoRecords(format!("{}: {}", name, e)))
+        }
+        Err(e) => Err(CliError::LookupFailed(format!("{}: {}", name, e))),
+    }
+}
+
+/// Checks the records of the given domains, or one record per line of stdin.
+fn lint(args: &mut ArgParser<impl Iterator<Item = String>>) -> Result<(), CliError> {
+    let mut stdin = false;
+    let mut raw_name = false;
+    let mut resolver_args = ResolverArgs::default();
+    let mut domains = Vec::new();
+
+    while let Some(arg) = args.next()? {
+        let option = match arg {
+            Arg::Positional(domain) => {
+                domains.push(domain);
+                continue;
+            }
+            Arg::Option(option) => option,
+        };
+
+        match option.as_str() {
+            "--help" | "-h" => {
+                print!("{}", LINT_HELP);
+                return Ok(());
+            }
+            "--stdin" => stdin = true,
+            "--raw-name" => raw_name = true,
+            _ => resolver_args.parse(&option, args)?,
+        }
+    }
+
+    // each source with how many records it has, and their issues
+    let mut sources = Vec::new();
+    if stdin {
+        let lines = read_stdin_records()?;
+        sources.push(("<stdin>".to_string(), lines.len(), lint_lines(&lines)));
+    }
+    if !domains.is_empty() {
+        let resolver = resolver_args.resolver()?;
+        for domain in domains {
+            let (name, lookup) = lookup_records(&*resolver, &domain, raw_name)?;
+            let count = lookup.records.len() + lookup.invalid.len();
+            sources.push((name, count, podns::lint::lint_lookup(&lookup)));
+        }
+    }
+    if sources.is_empty() {
+        return Err(CliError::Usage("lint requires a domain, or --stdin".into()));
+    }
+
+    let mut errors = 0;
+    for (source, count, issues) in &sources {
+        if issues.is_empty() {
+            println!("{}: {} records, no issues", source, count);
+            continue;
+        }
+
+        println!("{}:", source);
+        for issue in issues {
+            println!("{}", issue);
+        }
+        errors += issues
+            .iter()
+            .filter(|issue| issue.severity == podns::lint::Severity::Error)
+            .count();
+    }
+
+    if errors > 0 {
+        return Err(CliError::InvalidRecords(format!(
+            "{} invalid records",
+            errors
+        )));
+    }
+
+    Ok(())
+}
+
+/// Prints records in canonical form, or with `--diff`, only the ones that would change.
+fn fmt(args: &mut ArgParser<impl Iterator<Item = String>>) -> Result<(), CliError> {
+    let mut stdin = false;
+    let mut diff = false;
+    let mut domain = None;
+    let mut resolver_args = ResolverArgs::default();
+    let mut records = Vec::new();
+
+    while let Some(arg) = args.next()? {
+        let option = match arg {
+            Arg::Positional(record) => {
+                records.push(record);
+                continue;
+            }
+            Arg::Option(option) => option,
+        };
+
+        match option.as_str() {
+            "--help" | "-h" => {
+                print!("{}", FMT_HELP);
+                return Ok(());
+            }
+            "--stdin" => stdin = true,
+            "--diff" => diff = true,
+            // format the records that are currently published
+            "--domain" => domain = Some(args.value("a domain")?),
+            _ => resolver_args.parse(&option, args)?,
+        }
+    }
+
+    if stdin {
+        records.extend(read_stdin_records()?.into_iter().map(|(_, record)| record));
+    }
+    let mut invalid = 0;
+    if let Some(domain) = domain {
+        let resolver = resolver_args.resolver()?;
+        let (_, lookup) = lookup_records(&*resolver, &domain, false)?;
+        records.extend(lookup.records);
+
+        // records that aren't text can't be printed, so they are only reported
+        for record in &lookup.invalid {
+            eprintln!("Invalid record {:?}: not valid UTF-8", record.lossy());
+            invalid += 1;
+        }
+    }
+    if records.is_empty() && invalid == 0 {
+        return Err(CliError::Usage(
+            "fmt requires records, --stdin or --domain".into(),
+        ));
+    }
+
+    for record in &records {
+        // invalid records can't be formatted, but are kept so no record goes missing
+        if let Err(e) = podns::parse_record(record) {
+            eprintln!("Invalid record {:?}: {}", record, e);
+            invalid += 1;
+        }
+
+        let canonical = podns::canonicalize(record);
+        if !diff {
+            println!("{}", canonical);
+        } else if canonical != *record {
+            println!("-{}", record);
+            println!("+{}", canonical);
+        }
+    }
+
+    if invalid > 0 {
+        return Err(CliError::InvalidRecords(format!(
+            "{} invalid records, see `podns lint`",
+            invalid
+        )));
+    }
+
+    Ok(())
+}
+
+/// Serves the records in a config file over DNS, until the process is stopped.
+#[cfg(feature = "dns_server")]
+fn serve_dns(args: &mut ArgParser<impl Iterator<Item = String>>) -> Result<(), CliError> {
+    let mut config = None;
+    let mut listen = std::net::SocketAddr::from(([0, 0, 0, 0], 53));
+
+    while let Some(arg) = args.next()? {
+        let option = match arg {
+            Arg::Positional(arg) => return Err(CliError::Usage(format!("Unexpected {}", arg))),
+            Arg::Option(option) => option,
+        };
+
+        match option.as_str() {
+            "--help" | "-h" => {
+                print!("{}", SERVE_DNS_HELP);
+                return Ok(());
+            }
+            "--config" => config = Some(args.value("a path")?),
+            "--listen" => listen = parse_listen(&args.value("an address")?)?,
+            _ => return Err(unknown_option(&option)),
+        }
+    }
+
+    let config = config.ok_or_else(|| CliError::Usage("serve-dns requires --config".into()))?;
+    // every record is validated here, so a broken config never gets served
+    let zone = podns::PronounZone::load(&config).map_err(zone_error)?;
+    let server =
+        std::sync::Arc::new(podns::DnsServer::bind(listen, zone).map_err(CliError::IoError)?);
+
+    eprintln!(
+        "Serving pronoun records from {} on {}",
+        config,
+        server.local_addr().map_err(CliError::IoError)?
+    );
+
+    // reload the config on SIGHUP, keeping the old records if the new ones are invalid
+    #[cfg(unix)]
+    {
+        use signal_hook::{consts::SIGHUP, iterator::Signals};
+
+        let mut signals = Signals::new([SIGHUP]).map_err(CliError::IoError)?;
+        let server = server.clone();
+        std::thread::spawn(move || {
+            for _ in signals.forever() {
+                match podns::PronounZone::load(&config) {
+                    Ok(zone) => {
+                        server.replace_zone(zone);
+                        eprintln!("Reloaded {}", config);
+                    }
+                    Err(e) => eprintln!("Failed to reload {}: {}", config, e),
+                }
+            }
+        });
+    }
+
+    server.run().map_err(CliError::IoError)
+}
+
+/// Serves the HTTP API, until the process is stopped.
+#[cfg(feature = "http_server")]
+fn serve_http(args: &mut ArgParser<impl Iterator<Item = String>>) -> Result<(), CliError> {
+    let mut listen = std::net::SocketAddr::from(([127, 0, 0, 1], 8080));
+    let mut resolver_args = ResolverArgs::default();
+
+    while let Some(arg) = args.next()? {
+        let option = match arg {
+            Arg::Positional(arg) => return Err(CliError::Usage(format!("Unexpected {}", arg))),
+            Arg::Option(option) => option,
+        };
+
+        match option.as_str() {
+            "--help" | "-h" => {
+                print!("{}", SERVE_HTTP_HELP);
+                return Ok(());
+            }
+            "--listen" => listen = parse_listen(&args.value("an address")?)?,
+            _ => resolver_args.parse(&option, args)?,
+        }
+    }
+
+    let resolver = resolver_args.resolver()?;
+    let server = podns::HttpServer::bind(listen, resolver).map_err(CliError::IoError)?;
+
+    eprintln!(
+        "Serving the pronouns API on http://{}/v1/pronouns/{{domain}}",
+        server.local_addr().map_err(CliError::IoError)?
+    );
+
+    server.run();
+    Ok(())
+}
+
+#[cfg(any(feature = "dns_server", feature = "http_server"))]
+fn parse_listen(addr: &str) -> Result<std::net::SocketAddr, CliError> {
+    addr.parse()
+        .map_err(|_| CliError::Usage(format!("Invalid listen address: {}", addr)))
+}
+
+/// Invalid records in a zone are reported as such, other errors as they are.
+fn zone_error(e: io::Error) -> CliError {
+    if e.kind() == io::ErrorKind::InvalidData {
+        CliError::InvalidRecords(e.to_string())
+    } else {
+        CliError::IoError(e)
+    }
+}
+
+/// Prints the records for the given pronouns, validating them first.
+fn generate(args: &mut ArgParser<impl Iterator<Item = String>>) -> Result<(), CliError> {
+    let mut config: Option<String> = None;
+    let mut domain = None;
+    let mut ttl = None;
+    let mut target = podns::generate::ExportFormat::default();
+    let mut records = Vec::new();
+
+    while let Some(arg) = args.next()? {
+        let option = match arg {
+            Arg::Positional(record) => {
+                records.push(record);
+                continue;
+            }
+            Arg::Option(option) => option,
+        };
+
+        match option.as_str() {
+            "--help" | "-h" => {
+                print!("{}", GENERATE_HELP);
+                return Ok(());
+            }
+            "--config" => config = Some(args.value("a path")?),
+            "--domain" => domain = Some(args.value("a domain")?),
+            // the zone file, or the format of a DNS provider API
+            "--target" => {
+                target = args.value("a format")?.parse().map_err(CliError::Usage)?;
+            }
+            "--ttl" => {
+                let value = args.value("a number")?;
+                ttl = Some(
+                    value
+                        .parse::<u32>()
+                        .map_err(|_| CliError::Usage(format!("Invalid TTL: {}", value)))?,
+                );
+            }
+            _ => return Err(unknown_option(&option)),
+        }
+    }
+
+    let mut zone = match (config, domain) {
+        (Some(_), Some(_)) => {
+            return Err(CliError::Usage(
+                "Pass either --config or --domain, not both".into(),
+            ));
+        }
+        #[cfg(feature = "config")]
+        (Some(config), None) => {
+            if !records.is_empty() {
+                return Err(CliError::Usage(
+                    "Records can't be given as arguments with --config".into(),
+                ));
+            }
+            podns::PronounZone::load(config).map_err(zone_error)?
+        }
+        #[cfg(not(feature = "config"))]
+        (Some(_), None) => {
+            return Err(CliError::Usage(
+                "--config requires podns to be built with the config feature".into(),
+            ));
+        }
+        (None, Some(domain)) => {
+            let mut zone = podns::PronounZone::default();
+            zone.insert(&domain, records).map_err(zone_error)?;
+            zone
+        }
+        (None, None) => {
+            return Err(CliError::Usage(
+                "generate requires --domain and records, or --config".into(),
+            ));
+        }
+    };
+
+    if let Some(ttl) = ttl {
+        zone.set_ttl(ttl);
+    }
+
+    print!("{}", podns::generate::export(&zone, target));
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_args(args: &[&str]) -> Result<Vec<(Arg, Option<String>)>, CliError> {
+        let mut parser = ArgParser::new(args.iter().map(|arg| arg.to_string()));
+        let mut parsed = Vec::new();
+
+        while let Some(arg) = parser.next()? {
+            // options starting with `--v` take a value in these tests
+            let value = match &arg {
+                Arg::Option(option) if option.starts_with("--v") => Some(parser.value("a value")?),
+                _ => None,
+            };
+            parsed.push((arg, value));
+        }
+
+        Ok(parsed)
+    }
+
+    #[test]
+    fn test_arg_parser() {
+        let option = |name: &str, value: Option<&str>| {
+            (Arg::Option(name.to_string()), value.map(String::from))
+        };
+        let positional = |arg: &str| (Arg::Positional(arg.to_string()), None);
+
+        assert_eq!(
+            parse_args(&["a", "--flag", "--value", "x", "--value=y=z", "-", "-n"]).unwrap(),
+            vec![
+                positional("a"),
+                option("--flag", None),
+                option("--value", Some("x")),
+                option("--value", Some("y=z")),
+                positional("-"),
+                option("-n", None),
+            ]
+        );
+        assert_eq!(
+            parse_args(&["--", "--flag", "-n"]).unwrap(),
+            vec![positional("--flag"), positional("-n")]
+        );
+    }
+
+    #[test]
+    fn test_arg_parser_errors() {
+        let err = parse_args(&["--value"]).err().unwrap();
+        assert_eq!(err.exit_code(), 2);
+
+        // a value given to an option that doesn't take one
+        let err = parse_args(&["--flag=x", "a"]).err().unwrap();
+        assert_eq!(
+            err.to_string(),
+            "--flag doesn't take a value\n\nFor more information, try --help"
+        );
+    }
+
+    #[test]
+    fn test_describe_record() {
+        let record = podns::parse_record("she/her/her/hers; preferred # hi").unwrap();
+        assert_eq!(
+            describe_record(&record),
+            "  subject:               she\n  object:                her\n  possessive determiner: her\n  possessive pronoun:    hers\n  reflexive:             herself (from she/her)\n  tags:                  preferred\n  comment:               hi\n"
+        );
+
+        let record = podns::parse_record("xe/xem").unwrap();
+        assert!(
+            describe_record(&record)
+                .contains("reflexive:             - (maybe xemself, 90% sure)\n")
+        );
+
+        let record = podns::parse_record("elle/le; lang=es").unwrap();
+        assert!(
+            describe_record(&record).contains("  indirect object:       le (common in Spanish)\n")
+        );
+    }
+
+    #[test]
+    fn test_resolver_options() {
+        let run = |args: &[&str]| run(args.iter().map(|arg| arg.to_string()));
+
+        // every command that looks records up takes the same resolver options
+        #[cfg(feature = "doh")]
+        for command in [
+            &["resolve", "-n", "example.org"][..],
+            &["lint", "example.org"],
+            &["fmt", "--domain", "example.org"],
+        ] {
+            let mut args = vec![command[0], "--doh", "http://127.0.0.1:1/dns-query"];
+            args.extend(&command[1..]);
+            let error = run(&args).err().unwrap();
+            assert_eq!(error.exit_code(), 4, "{} {}", command[0], error);
+        }
+
+        #[cfg(not(feature = "dns_resolve"))]
+        assert_eq!(run(&["lint", "example.org"]).err().unwrap().exit_code(), 2);
+        #[cfg(not(feature = "doh"))]
+        assert_eq!(
+            run(&[
+                "lint",
+                "--doh",
+                "https://dns.example/dns-query",
+                "example.org"
+            ])
+            .err()
+            .unwrap()
+            .exit_code(),
+            2
+        );
+    }
+
+    #[test]
+    fn test_read_records() {
+        let records = read_records("she/her\n\n  \nthey/them\r\n".as_bytes()).unwrap();
+        assert_eq!(
+            records,
+            vec![(1, "she/her".to_string()), (4, "they/them".to_string())]
+        );
+
+        // issues are reported at the line of their record, blank lines included
+        let lines = read_records("she/her\n\nshe/\n".as_bytes()).unwrap();
+        let issues = lint_lines(&lines);
+        assert_eq!(issues.len(), 1);
+        assert!(issues[0].to_string().contains("--> record 3\n"));
+    }
+
+    #[test]
+    fn test_exit_codes() {
+        let run = |args: &[&str]| run(args.iter().map(|arg| arg.to_string()));
+
+        assert!(run(&["--version"]).is_ok());
+        assert!(run(&["help", "lint"]).is_ok());
+        assert!(run(&["parse", "she/her", "they/them; preferred"]).is_ok());
+        assert_eq!(run(&["parse", "she/"]).err().unwrap().exit_code(), 5);
+        assert_eq!(run(&["parse", "--bogus"]).err().unwrap().exit_code(), 2);
+        assert_eq!(run(&["--non-interactive"]).err().unwrap().exit_code(), 2);
+        assert_eq!(
+            run(&["generate", "--domain", "example.org", "she/her/"])
+                .err()
+                .unwrap()
+                .exit_code(),
+            5
+        );
     }
 }
@@ -0,0 +1,172 @@
+//! Strategies for sorting pronoun records, for when the order of [`PronounRecord`]'s `Ord`
+//! isn't the one that is wanted.
+//!
+//! Once records are sorted, the order they were published in is lost. To get it back, parse
+//! them with [`parse_records_with`](crate::parse_records_with), which gives each record's
+//! index, and sort them with [`sort_parsed`] and [`PublisherOrder`].
+//!
+//! ```
+//! use podns::order::{Alphabetical, sort_records};
+//!
+//! let mut records = podns::parse_records(&["they/them", "she/her; preferred", "*"]);
+//! sort_records(&mut records, &Alphabetical);
+//!
+//! let records = records.iter().map(|record| record.to_string()).collect::<Vec<_>>();
+//! assert_eq!(records[0], "*");
+//! assert_eq!(records[1], "she/her/her/hers/herself; preferred");
+//! ```
+
+use std::cmp::Ordering;
+
+use crate::pronouns::{ParsedRecord, PronounRecord};
+
+/// A way to order pronoun records.
+///
+/// Closures that compare two records are orders too.
+pub trait RecordOrder {
+    fn compare(&self, a: &PronounRecord, b: &PronounRecord) -> Ordering;
+}
+
+impl<F> RecordOrder for F
+where
+    F: Fn(&PronounRecord, &PronounRecord) -> Ordering,
+{
+    fn compare(&self, a: &PronounRecord, b: &PronounRecord) -> Ordering {
+        self(a, b)
+    }
+}
+
+/// A way to order parsed records, which can also use where they came from.
+///
+/// Every [`RecordOrder`] is one too, comparing the records themselves.
+pub trait ParsedOrder {
+    fn compare(&self, a: &ParsedRecord, b: &ParsedRecord) -> Ordering;
+}
+
+impl<O: RecordOrder> ParsedOrder for O {
+    fn compare(&self, a: &ParsedRecord, b: &ParsedRecord) -> Ordering {
+        RecordOrder::compare(self, &a.record, &b.record)
+    }
+}
+
+/// The order the records were published in, by [`ParsedRecord::index`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PublisherOrder;
+
+impl ParsedOrder for PublisherOrder {
+    fn compare(&self, a: &ParsedRecord, b: &ParsedRecord) -> Ordering {
+        a.index.cmp(&b.index)
+    }
+}
+
+/// Preferred sets first, then the other sets, `*` and `!`, like [`PronounRecord`]'s `Ord`.
+///
+/// This is the order [`parse_records`](crate::parse_records) uses.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PreferredFirst;
+
+impl RecordOrder for PreferredFirst {
+    fn compare(&self, a: &PronounRecord, b: &PronounRecord) -> Ordering {
+        a.cmp(b)
+    }
+}
+
+/// By the records as they would be published, ignoring whether they are preferred.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Alphabetical;
+
+impl RecordOrder for Alphabetical {
+    fn compare(&self, a: &PronounRecord, b: &PronounRecord) -> Ordering {
+        a.canonical().cmp(&b.canonical()).then_with(|| a.cmp(b))
+    }
+}
+
+/// Sorts `records` by `order`, keeping records that compare as equal in the order they are in.
+pub fn sort_records(records: &mut [PronounRecord], order: &impl RecordOrder) {
+    records.sort_by(|a, b| order.compare(a, b));
+}
+
+/// Sorts parsed `records` by `order`, keeping records that compare as equal in the order they
+/// are in.
+///
+/// ```
+/// use podns::order::{PreferredFirst, PublisherOrder, sort_parsed};
+///
+/// let records = ["they/them", "she/her; preferred"];
+/// let (mut records, _) = podns::parse_records_with(&records, &Default::default());
+/// assert_eq!(records[0].raw, "she/her; preferred");
+///
+/// sort_parsed(&mut records, &PublisherOrder);
+/// assert_eq!(records[0].raw, "they/them");
+/// ```
+pub fn sort_parsed(records: &mut [ParsedRecord], order: &impl ParsedOrder) {
+    records.sort_by(|a, b| order.compare(a, b));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_record;
+
+    fn sorted(records: &[&str], order: &impl RecordOrder) -> Vec<String> {
+        let mut records = records
+            .iter()
+            .map(|record| parse_record(record).unwrap())
+            .collect::<Vec<_>>();
+        sort_records(&mut records, order);
+        records.iter().map(PronounRecord::canonical).collect()
+    }
+
+    const RECORDS: [&str; 4] = ["they/them", "!", "she/her; preferred", "ae/aer"];
+
+    #[test]
+    fn test_preferred_first() {
+        assert_eq!(
+            sorted(&RECORDS, &PreferredFirst),
+            ["she/her; preferred", "ae/aer", "they/them", "!"]
+        );
+    }
+
+    #[test]
+    fn test_alphabetical() {
+        assert_eq!(
+            sorted(&RECORDS, &Alphabetical),
+            ["!", "ae/aer", "she/her; preferred", "they/them"]
+        );
+    }
+
+    #[test]
+    fn test_publisher_order() {
+        let (mut records, _) = crate::parse_records_with(&RECORDS, &Default::default());
+        let raw = |records: &[ParsedRecord]| {
+            records
+                .iter()
+                .map(|parsed| parsed.raw.clone())
+                .collect::<Vec<_>>()
+        };
+
+        // both of the other orders move the records around
+        sort_parsed(&mut records, &PreferredFirst);
+        assert_ne!(raw(&records), RECORDS);
+        sort_parsed(&mut records, &Alphabetical);
+        assert_ne!(raw(&records), RECORDS);
+
+        sort_parsed(&mut records, &PublisherOrder);
+        assert_eq!(raw(&records), RECORDS);
+    }
+
+    #[test]
+    fn test_closure() {
+        // by how many forms are given, most first
+        let by_forms = |a: &PronounRecord, b: &PronounRecord| {
+            b.canonical()
+                .matches('/')
+                .count()
+                .cmp(&a.canonical().matches('/').count())
+        };
+        assert_eq!(
+            sorted(&["she/her", "xe/xem/xyr", "he/him/his"], &by_forms),
+            ["xe/xem/xyr", "he/him/his", "she/her"]
+        );
+    }
+}
@@ -0,0 +1,578 @@
+//! Formats the results of pronoun lookups for other programs to read, as JSON, NDJSON or CSV.
+//!
+//! Unlike [`PronounLookup`](crate::PronounLookup), a [`DomainReport`] keeps every record as it
+//! was published, including the ones that fail to parse.
+
+use std::fmt::{Display, Write};
+use std::io;
+use std::str::FromStr;
+
+use serde_json::{Value, json};
+
+use crate::examples::sentences;
+use crate::language::resolve_forms;
+use crate::lookup::{InvalidTxtRecord, TxtLookup};
+use crate::parser::{ParserError, parse_record};
+use crate::pronouns::{PronounForm, PronounRecord, PronounSet, Provenance, ResolvedForm};
+
+/// How to print lookup results.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OutputFormat {
+    /// One record per line, for people
+    #[default]
+    Text,
+    /// A JSON array with an object per domain
+    Json,
+    /// A JSON object per domain, one per line
+    Ndjson,
+    /// A header, then a row per record
+    Csv,
+}
+
+impl OutputFormat {
+    pub const ALL: [OutputFormat; 4] = [
+        OutputFormat::Text,
+        OutputFormat::Json,
+        OutputFormat::Ndjson,
+        OutputFormat::Csv,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            OutputFormat::Text => "text",
+            OutputFormat::Json => "json",
+            OutputFormat::Ndjson => "ndjson",
+            OutputFormat::Csv => "csv",
+        }
+    }
+}
+
+impl Display for OutputFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+impl FromStr for OutputFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        OutputFormat::ALL
+            .into_iter()
+            .find(|format| format.name().eq_ignore_ascii_case(s))
+            .ok_or_else(|| format!("unknown output format {:?}", s))
+    }
+}
+
+/// A TXT record, and what it parsed to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RecordReport {
+    pub raw: String,
+    pub parsed: Result<PronounRecord, ParserError>,
+}
+
+/// The outcome of looking up the records of one domain.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DomainReport {
+    /// The domain as it was asked for
+    pub domain: String,
+    /// The name that was queried, usually `pronouns.<domain>`
+    pub name: String,
+    /// The records in the order they were received, empty if the lookup failed
+    pub records: Vec<RecordReport>,
+    pub authenticated: bool,
+    pub ttl: Option<u32>,
+    /// Why the lookup failed
+    pub error: Option<String>,
+    /// The records that aren't text, which aren't in `records`
+    pub invalid: Vec<InvalidTxtRecord>,
+}
+
+impl DomainReport {
+    /// Parses every record of `lookup`.
+    pub fn from_lookup(domain: &str, lookup: TxtLookup) -> Self {
+        DomainReport {
+            domain: domain.to_owned(),
+            name: lookup.name,
+            records: lookup
+                .records
+                .into_iter()
+                .map(|raw| RecordReport {
+                    parsed: parse_record(&raw),
+                    raw,
+                })
+                .collect(),
+            authenticated: lookup.authenticated,
+            ttl: lookup.ttl,
+            error: None,
+            invalid: lookup.invalid,
+        }
+    }
+
+    pub fn from_error(domain: &str, name: &str, error: &io::Error) -> Self {
+        DomainReport {
+            domain: domain.to_owned(),
+            name: name.to_owned(),
+            records: Vec::new(),
+            authenticated: false,
+            ttl: None,
+            error: Some(error.to_string()),
+            invalid: Vec::new(),
+        }
+    }
+
+    /// The records that parsed, sorted like [`parse_records`](crate::parse_records) does.
+    pub fn valid_records(&self) -> Vec<&PronounRecord> {
+        let mut records = self
+            .records
+            .iter()
+            .filter_map(|record| record.parsed.as_ref().ok())
+            .collect::<Vec<&PronounRecord>>();
+        records.sort();
+        records
+    }
+}
+
+/// Formats `reports` in the given format, ending with a newline.
+pub fn format_reports(reports: &[DomainReport], format: OutputFormat) -> String {
+    match format {
+        OutputFormat::Text => format_text(reports, false),
+        OutputFormat::Json => {
+            let domains = reports
+                .iter()
+                .map(|report| format!("  {}", report_json(report)))
+                .collect::<Vec<String>>();
+            if domains.is_empty() {
+                "[]\n".to_string()
+            } else {
+                format!("[\n{}\n]\n", domains.join(",\n"))
+            }
+        }
+        OutputFormat::Ndjson => reports
+            .iter()
+            .map(|report| format!("{}\n", report_json(report)))
+            .collect(),
+        OutputFormat::Csv => csv(reports),
+    }
+}
+
+/// The valid records of each domain, under a heading if there are several domains. Forms that
+/// are inferred rather than given are in brackets, as in `she/her/(her)/(hers)/(herself)`.
+///
+/// With `examples`, each record is followed by [example sentences](crate::examples) for it.
+pub fn format_text(reports: &[DomainReport], examples: bool) -> String {
+    let mut out = String::new();
+    let indent = if reports.len() > 1 { "  " } else { "" };
+
+    for report in reports {
+        if reports.len() > 1 {
+            let _ = writeln!(out, "{}:", report.domain);
+        }
+        let records = report.valid_records();
+        if let Some(error) = &report.error {
+            let _ = writeln!(out, "{}error: {}", indent, error);
+        } else if records.is_empty() {
+            let _ = writeln!(out, "{}no valid pronoun records", indent);
+        }
+        for record in records {
+            // inferred forms are in brackets, so they aren't mistaken for declared ones
+            if report.authenticated {
+                // before the record, so it isn't read as part of the comment
+                let _ = writeln!(out, "{}[verified] {:#}", indent, record);
+            } else {
+                let _ = writeln!(out, "{}{:#}", indent, record);
+            }
+
+            if let Some(set) = record.set.as_ref().filter(|_| examples) {
+                for sentence in sentences(set) {
+                    let _ = writeln!(out, "{}    {}", indent, sentence);
+                }
+            }
+        }
+    }
+
+    out
+}
+
+fn report_json(report: &DomainReport) -> Value {
+    let records = report
+        .records
+        .iter()
+        .map(|record| {
+            let (parsed, error) = match &record.parsed {
+                Ok(parsed) => (record_json(parsed), None),
+                Err(e) => (Value::Null, Some(e.to_string())),
+            };
+            json!({
+                "raw": record.raw,
+                "parsed": parsed,
+                "error": error,
+            })
+        })
+        .collect::<Vec<Value>>();
+
+    json!({
+        "domain": report.domain,
+        "name": report.name,
+        "authenticated": report.authenticated,
+        "ttl": report.ttl,
+        "error": report.error,
+        "records": records,
+        "invalid": report
+            .invalid
+            .iter()
+            .map(|invalid| json!({ "raw": invalid.lossy(), "error": invalid.to_string() }))
+            .collect::<Vec<Value>>(),
+    })
+}
+
+/// A parsed record, as both `--format json` and the HTTP API show it.
+pub(crate) fn record_json(record: &PronounRecord) -> Value {
+    let set = match &record.set {
+        Some(set @ PronounSet::Defined { definition, tags }) => {
+            let forms = resolve_forms(set.language(), definition);
+            json!({
+                "type": "defined",
+                "subject": forms[0].1.value,
+                "object": forms[1].1.value,
+                "possessive_determiner": forms[2].1.value,
+                "possessive_pronoun": forms[3].1.value,
+                "reflexive": forms[4].1.value,
+                "inferred": inferred_forms(&forms),
+                "tags": tags.iter().map(|tag| tag.to_string()).collect::<Vec<String>>(),
+            })
+        }
+        Some(PronounSet::Any) => json!({ "type": "any" }),
+        Some(PronounSet::None) => json!({ "type": "none" }),
+        None => Value::Null,
+    };
+
+    json!({
+        "record": record.to_string(),
+        "set": set,
+        "comment": record.comment,
+    })
+}
+
+/// The names of the forms that were inferred from a common set, rather than given.
+fn inferred_forms(forms: &[(&'static str, ResolvedForm); 5]) -> Vec<&'static str> {
+    forms
+        .iter()
+        .zip(PronounForm::ALL)
+        .filter(|((_, resolved), _)| resolved.value.is_some())
+        .filter(|((_, resolved), _)| resolved.provenance != Provenance::Explicit)
+        .map(|(_, form)| form.name())
+        .collect()
+}
+
+const CSV_HEADER: [&str; 16] = [
+    "domain",
+    "name",
+    "authenticated",
+    "ttl",
+    "error",
+    "index",
+    "raw",
+    "parse_error",
+    "type",
+    "subject",
+    "object",
+    "possessive_determiner",
+    "possessive_pronoun",
+    "reflexive",
+    "tags",
+    "comment",
+];
+
+/// A row per record, or a single row for a domain without records, with the lookup repeated
+/// on every row. Tags are separated by `;`.
+fn csv(reports: &[DomainReport]) -> String {
+    let mut out = String::new();
+    csv_row(&mut out, CSV_HEADER.map(String::from));
+
+    for report in reports {
+        let lookup = [
+            report.domain.clone(),
+            report.name.clone(),
+            report.authenticated.to_string(),
+            report.ttl.map(|ttl| ttl.to_string()).unwrap_or_default(),
+            report.error.clone().unwrap_or_default(),
+        ];
+
+        if report.records.is_empty() && report.invalid.is_empty() {
+            let mut row = lookup.to_vec();
+            row.resize(CSV_HEADER.len(), String::new());
+            csv_row(&mut out, row);
+        }
+
+        for (index, record) in report.records.iter().enumerate() {
+            let mut row = lookup.to_vec();
+            row.push(index.to_string());
+            row.push(record.raw.clone());
+
+            match &record.parsed {
+                Ok(parsed) => {
+                    row.push(String::new());
+                    row.extend(record_fields(parsed));
+                }
+                Err(e) => row.push(e.to_string()),
+            }
+
+            row.resize(CSV_HEADER.len(), String::new());
+            csv_row(&mut out, row);
+        }
+
+        // records that aren't text have no index, since they aren't in `records`
+        for invalid in &report.invalid {
+            let mut row = lookup.to_vec();
+            row.extend([
+                String::new(),
+                invalid.lossy().into_owned(),
+                invalid.to_string(),
+            ]);
+            row.resize(CSV_HEADER.len(), String::new());
+            csv_row(&mut out, row);
+        }
+    }
+
+    out
+}
+
+/// The `type` to `comment` columns.
+fn record_fields(record: &PronounRecord) -> [String; 8] {
+    let comment = record.comment.clone().unwrap_or_default();
+
+    match &record.set {
+        Some(set @ PronounSet::Defined { definition, tags }) => {
+            let forms = resolve_forms(set.language(), definition)
+                .map(|(_, resolved)| resolved.value.unwrap_or_default().to_string());
+            let [
+                subject,
+                object,
+                possessive_determiner,
+                possessive_pronoun,
+                reflexive,
+            ] = forms;
+            [
+                "defined".to_string(),
+                subject,
+                object,
+                possessive_determiner,
+                possessive_pronoun,
+                reflexive,
+                tags.iter()
+                    .map(|tag| tag.to_string())
+                    .collect::<Vec<String>>()
+                    .join(";"),
+                comment,
+            ]
+        }
+        set => {
+            let kind = match set {
+                Some(PronounSet::Any) => "any",
+                Some(PronounSet::None) => "none",
+                _ => "",
+            };
+            let mut fields: [String; 8] = Default::default();
+            fields[0] = kind.to_string();
+            fields[7] = comment;
+            fields
+        }
+    }
+}
+
+/// Writes a row as in RFC 4180, quoting the fields that need it.
+fn csv_row(out: &mut String, fields: impl IntoIterator<Item = String>) {
+    let fields = fields
+        .into_iter()
+        .map(|field| {
+            if field.contains([',', '"', '\n', '\r']) {
+                format!("\"{}\"", field.replace('"', "\"\""))
+            } else {
+                field
+            }
+        })
+        .collect::<Vec<String>>();
+
+    out.push_str(&fields.join(","));
+    out.push_str("\r\n");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn reports() -> Vec<DomainReport> {
+        vec![
+            DomainReport::from_lookup(
+                "kinda.red",
+                TxtLookup {
+                    name: "pronouns.kinda.red".to_string(),
+                    records: vec![
+                        "they/them # \"hi\", there".to_string(),
+                        "she/her; preferred".to_string(),
+                        "she/".to_string(),
+                    ],
+                    authenticated: true,
+                    ttl: Some(300),
+                    invalid: Vec::new(),
+                },
+            ),
+            DomainReport::from_error(
+                "missing.example",
+                "pronouns.missing.example",
+                &io::Error::new(io::ErrorKind::NotFound, "no such name"),
+            ),
+        ]
+    }
+
+    #[test]
+    fn test_format_from_str() {
+        assert_eq!("NDJSON".parse(), Ok(OutputFormat::Ndjson));
+        assert!("yaml".parse::<OutputFormat>().is_err());
+    }
+
+    #[test]
+    fn test_text() {
+        assert_eq!(
+            format_reports(&reports(), OutputFormat::Text),
+            "kinda.red:\n  [verified] she/her/(her)/(hers)/(herself); preferred\n  [verified] they/them/(their)/(theirs)/(themself) # \"hi\", there\nmissing.example:\n  error: no such name\n"
+        );
+    }
+
+    #[test]
+    fn test_text_examples() {
+        let text = format_text(&reports()[..1], true);
+        assert!(text.starts_with(
+            "[verified] she/her/(her)/(hers)/(herself); preferred\n    She went to the park.\n"
+        ));
+        assert!(text.contains("\n    They rode the bike themself.\n"));
+    }
+
+    #[test]
+    fn test_json() {
+        let json = format_reports(&reports(), OutputFormat::Json);
+        let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(value[0]["domain"], "kinda.red");
+        assert_eq!(value[0]["ttl"], 300);
+        assert_eq!(value[0]["authenticated"], true);
+        assert_eq!(value[0]["records"][0]["raw"], "they/them # \"hi\", there");
+        assert_eq!(
+            value[0]["records"][1]["parsed"]["set"]["tags"],
+            serde_json::json!(["preferred"])
+        );
+        assert_eq!(
+            value[0]["records"][1]["parsed"]["set"]["inferred"],
+            serde_json::json!(["possessive_determiner", "possessive_pronoun", "reflexive"])
+        );
+        assert_eq!(value[0]["records"][2]["parsed"], serde_json::Value::Null);
+        assert_eq!(
+            value[0]["records"][2]["error"],
+            ParserError::NotEnoughPronounParts.to_string()
+        );
+        assert_eq!(value[1]["error"], "no such name");
+        assert_eq!(value[1]["records"], serde_json::json!([]));
+    }
+
+    #[test]
+    fn test_ndjson() {
+        let ndjson = format_reports(&reports(), OutputFormat::Ndjson);
+        let lines = ndjson.lines().collect::<Vec<&str>>();
+
+        assert_eq!(lines.len(), 2);
+        for line in lines {
+            serde_json::from_str::<serde_json::Value>(line).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_other_languages() {
+        let reports = [DomainReport::from_lookup(
+            "example.org",
+            TxtLookup {
+                name: "pronouns.example.org".to_string(),
+                records: vec![
+                    "she/her; lang=fr".to_string(),
+                    "er/ihn; lang=de".to_string(),
+                ],
+                authenticated: false,
+                ttl: None,
+                invalid: Vec::new(),
+            },
+        )];
+
+        // nothing is inferred from English, and forms of known languages come from their own
+        // common sets
+        assert_eq!(
+            format_reports(&reports, OutputFormat::Text),
+            "er/ihn/(ihm)/(sein)/(seiner); lang=de\nshe/her; lang=fr\n"
+        );
+
+        let json = format_reports(&reports, OutputFormat::Json);
+        let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+        let french = &value[0]["records"][0]["parsed"]["set"];
+        assert_eq!(french["possessive_determiner"], serde_json::Value::Null);
+        assert_eq!(french["inferred"], serde_json::json!([]));
+        let german = &value[0]["records"][1]["parsed"]["set"];
+        assert_eq!(german["possessive_determiner"], "ihm");
+        assert_eq!(
+            german["inferred"],
+            serde_json::json!(["possessive_determiner", "possessive_pronoun", "reflexive"])
+        );
+
+        let csv = format_reports(&reports, OutputFormat::Csv);
+        let rows = csv.split_terminator("\r\n").collect::<Vec<&str>>();
+        assert!(rows[1].contains(",defined,she,her,,,,lang=fr,"));
+        assert!(rows[2].contains(",defined,er,ihn,ihm,sein,seiner,lang=de,"));
+    }
+
+    #[test]
+    fn test_invalid_records() {
+        let report = DomainReport::from_lookup(
+            "example.org",
+            TxtLookup {
+                name: "pronouns.example.org".to_string(),
+                records: vec!["she/her".to_string()],
+                authenticated: false,
+                ttl: None,
+                invalid: vec![InvalidTxtRecord {
+                    data: b"he/h\xffm".to_vec(),
+                }],
+            },
+        );
+        let reports = [report];
+
+        let json = format_reports(&reports, OutputFormat::Json);
+        let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(value[0]["records"][0]["raw"], "she/her");
+        assert_eq!(value[0]["invalid"][0]["raw"], "he/h\u{fffd}m");
+
+        let csv = format_reports(&reports, OutputFormat::Csv);
+        let rows = csv.split_terminator("\r\n").collect::<Vec<&str>>();
+        assert_eq!(rows.len(), 3);
+        assert!(rows[2].starts_with("example.org,pronouns.example.org,false,,,,he/h\u{fffd}m,"));
+    }
+
+    #[test]
+    fn test_csv() {
+        let csv = format_reports(&reports(), OutputFormat::Csv);
+        let rows = csv.split_terminator("\r\n").collect::<Vec<&str>>();
+
+        assert_eq!(rows.len(), 5);
+        assert_eq!(rows[0], CSV_HEADER.join(","));
+        assert_eq!(
+            rows[1],
+            "kinda.red,pronouns.kinda.red,true,300,,0,\"they/them # \"\"hi\"\", there\",,defined,they,them,their,theirs,themself,,\"\"\"hi\"\", there\""
+        );
+        assert_eq!(
+            rows[2],
+            "kinda.red,pronouns.kinda.red,true,300,,1,she/her; preferred,,defined,she,her,her,hers,herself,preferred,"
+        );
+        assert!(rows[3].starts_with("kinda.red,pronouns.kinda.red,true,300,,2,she/,a pronoun set"));
+        assert_eq!(
+            rows[4],
+            "missing.example,pronouns.missing.example,false,,no such name,,,,,,,,,,,"
+        );
+    }
+}
@@ -1,6 +1,8 @@
+use std::ops::Range;
+
 use crate::pronouns::{PronounDef, PronounRecord, PronounSet, PronounTag};
 
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum ParserError {
     NotEnoughPronounParts,
     TooManyPronounParts,
@@ -15,6 +17,42 @@ pub enum ParserError {
     InvalidFormat,
 }
 
+impl std::fmt::Display for ParserError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ParserError::NotEnoughPronounParts => {
+                write!(f, "a pronoun set needs at least a subject and an object")
+            }
+            ParserError::TooManyPronounParts => {
+                write!(f, "a pronoun set has at most five parts")
+            }
+            ParserError::InvalidTag => write!(f, "unknown tag"),
+            ParserError::TrailingCharacters => write!(f, "unexpected characters at the end"),
+            ParserError::TrailingSlash => write!(f, "`/` is not followed by a pronoun"),
+            ParserError::Empty => write!(f, "the record is empty"),
+            ParserError::TagsNotAllowed => write!(f, "tags are only allowed on pronoun sets"),
+            ParserError::InvalidFormat => write!(f, "unexpected character"),
+        }
+    }
+}
+
+/// A [`ParserError`], with the byte range of the input it was found at.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SpannedParserError {
+    pub error: ParserError,
+    pub span: Range<usize>,
+}
+
+impl std::fmt::Display for SpannedParserError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} at {}..{}",
+            self.error, self.span.start, self.span.end
+        )
+    }
+}
+
 enum ParserState {
     BuildingPronounDef { n: u8, trailing_slash: bool },
     BuildingTags,
@@ -43,12 +81,24 @@ impl Default for Parser {
 struct ParseStream<'a> {
     chars: std::str::Chars<'a>,
     peeked: Option<char>,
+    // byte offset of the next character
+    offset: usize,
 }
 impl<'a> ParseStream<'a> {
     fn new(input: &'a str) -> Self {
         ParseStream {
             chars: input.chars(),
             peeked: None,
+            offset: 0,
+        }
+    }
+
+    /// An error at the next character.
+    fn error(&mut self, error: ParserError) -> SpannedParserError {
+        let len = self.peek().map_or(0, |c| c.len_utf8());
+        SpannedParserError {
+            error,
+            span: self.offset..self.offset + len,
         }
     }
 
@@ -61,11 +111,12 @@ impl<'a> ParseStream<'a> {
     }
 
     fn next(&mut self) -> Option<char> {
-        if let Some(c) = self.peeked.take() {
-            Some(c)
-        } else {
-            self.chars.next()
-        }
+        let c = match self.peeked.take() {
+            Some(c) => Some(c),
+            None => self.chars.next(),
+        };
+        self.offset += c.map_or(0, |c| c.len_utf8());
+        c
     }
 
     fn skip_while<F: Fn(char) -> bool>(&mut self, predicate: F) {
@@ -104,6 +155,20 @@ impl<'a> ParseStream<'a> {
 }
 
 pub fn parse_record(input: &str) -> Result<PronounRecord, ParserError> {
+    parse_record_spanned(input).map_err(|e| e.error)
+}
+
+/// Like [`parse_record`], but reports where in `input` the error is.
+pub fn parse_record_spanned(input: &str) -> Result<PronounRecord, SpannedParserError> {
+    // the whole record, for errors that are not about any one part of it
+    let whole = |error| {
+        let start = input.len() - input.trim_start().len();
+        SpannedParserError {
+            error,
+            span: start..input.trim_end().len().max(start),
+        }
+    };
+
     let mut parse_stream = ParseStream::new(input);
     let mut parser = Parser::default();
 
@@ -114,37 +179,46 @@ pub fn parse_record(input: &str) -> Result<PronounRecord, ParserError> {
             ';' => {
                 if let ParserState::BuildingPronounDef {
                     n: _,
-                    trailing_slash,
+                    trailing_slash: true,
                 } = parser.state
-                    && trailing_slash
                 {
-                    return Err(ParserError::TrailingSlash);
+                    return Err(trailing_slash_error(input, parse_stream.offset));
                 }
                 // tag separator
                 match parser.state {
-                    ParserState::BuildingPronounDef { n, trailing_slash } => {
+                    ParserState::BuildingPronounDef { n, .. } => {
                         if n < 2 {
-                            return Err(ParserError::NotEnoughPronounParts);
-                        }
-                        if trailing_slash {
-                            return Err(ParserError::TrailingSlash);
+                            return Err(whole(ParserError::NotEnoughPronounParts));
                         }
 
                         parser.state = ParserState::BuildingTags;
                     }
                     ParserState::BuildingTags => {}
                     ParserState::CommentOrEnd => {
-                        return Err(ParserError::TagsNotAllowed);
+                        return Err(parse_stream.error(ParserError::TagsNotAllowed));
                     }
                 }
 
+                // the tags run until the comment, or the end of the record
+                let tags_span = parse_stream.offset
+                    ..input[parse_stream.offset..]
+                        .find('#')
+                        .map_or(input.trim_end().len(), |i| {
+                            input[..parse_stream.offset + i].trim_end().len()
+                        });
+
                 let builder_set = match &mut parser.def_builder {
                     Some(set) => set,
-                    None => return Err(ParserError::NotEnoughPronounParts),
+                    None => return Err(whole(ParserError::NotEnoughPronounParts)),
                 };
                 let tags = match builder_set {
                     PronounSet::Defined { tags, .. } => tags,
-                    _ => return Err(ParserError::TagsNotAllowed),
+                    _ => {
+                        return Err(SpannedParserError {
+                            error: ParserError::TagsNotAllowed,
+                            span: tags_span,
+                        });
+                    }
                 };
 
                 // process tag
@@ -152,11 +226,15 @@ pub fn parse_record(input: &str) -> Result<PronounRecord, ParserError> {
                 parse_stream.skip_while(|c| c == ';');
                 parse_stream.skip_whitespace();
 
+                let tag_start = parse_stream.offset;
                 let tag_string = parse_stream
                     .take_while(|ch| ch != ';' && ch != '#' && !ch.is_whitespace())
                     .to_lowercase();
 
-                let tag = PronounTag::from_string(tag_string).ok_or(ParserError::InvalidTag)?;
+                let tag = PronounTag::from_string(tag_string).ok_or(SpannedParserError {
+                    error: ParserError::InvalidTag,
+                    span: tag_start..parse_stream.offset,
+                })?;
                 if !tags.contains(&tag) {
                     // check for duplicates
                     tags.push(tag);
@@ -167,11 +245,10 @@ pub fn parse_record(input: &str) -> Result<PronounRecord, ParserError> {
             '#' => {
                 if let ParserState::BuildingPronounDef {
                     n: _,
-                    trailing_slash,
+                    trailing_slash: true,
                 } = parser.state
-                    && trailing_slash
                 {
-                    return Err(ParserError::TrailingSlash);
+                    return Err(trailing_slash_error(input, parse_stream.offset));
                 }
                 // comment, consume rest of line and add to comment
                 parse_stream.next(); // skip the '#'
@@ -185,7 +262,7 @@ pub fn parse_record(input: &str) -> Result<PronounRecord, ParserError> {
                     match c {
                         '*' => {
                             if trailing_slash {
-                                return Err(ParserError::TrailingSlash);
+                                return Err(trailing_slash_error(input, parse_stream.offset));
                             }
                             parser.def_builder = Some(PronounSet::Any);
                             parser.state = ParserState::BuildingTags;
@@ -195,7 +272,7 @@ pub fn parse_record(input: &str) -> Result<PronounRecord, ParserError> {
                         }
                         '!' => {
                             if trailing_slash {
-                                return Err(ParserError::TrailingSlash);
+                                return Err(trailing_slash_error(input, parse_stream.offset));
                             }
                             parser.def_builder = Some(PronounSet::None);
                             parser.state = ParserState::BuildingTags;
@@ -206,14 +283,21 @@ pub fn parse_record(input: &str) -> Result<PronounRecord, ParserError> {
                         _ => {}
                     }
 
+                    let part_start = parse_stream.offset;
                     let part = parse_stream
                         .take_while(|ch| ch != '/' && ch != ';' && ch != '#' && ch != '"');
+                    let part_span = part_start..part_start + part.trim_end().len();
                     let part = part.trim_end().to_lowercase();
 
                     if part.is_empty() {
-                        return Err(ParserError::InvalidFormat);
+                        return Err(parse_stream.error(ParserError::InvalidFormat));
                     }
 
+                    let too_many_parts = SpannedParserError {
+                        error: ParserError::TooManyPronounParts,
+                        span: part_span,
+                    };
+
                     let pronoun_set =
                         parser
                             .def_builder
@@ -230,7 +314,7 @@ pub fn parse_record(input: &str) -> Result<PronounRecord, ParserError> {
 
                     let pronoun_def = match pronoun_set {
                         PronounSet::Defined { definition, .. } => definition,
-                        _ => return Err(ParserError::TooManyPronounParts),
+                        _ => return Err(too_many_parts),
                     };
 
                     let part_to_update = match n {
@@ -239,7 +323,7 @@ pub fn parse_record(input: &str) -> Result<PronounRecord, ParserError> {
                         2 => pronoun_def.possessive_determiner.get_or_insert_default(),
                         3 => pronoun_def.possessive_pronoun.get_or_insert_default(),
                         4 => pronoun_def.reflexive.get_or_insert_default(),
-                        _ => return Err(ParserError::TooManyPronounParts),
+                        _ => return Err(too_many_parts),
                     };
 
                     part_to_update.push_str(&part);
@@ -257,11 +341,8 @@ pub fn parse_record(input: &str) -> Result<PronounRecord, ParserError> {
                         parser.state = ParserState::BuildingTags;
                     }
                 }
-                ParserState::BuildingTags => {
-                    return Err(ParserError::InvalidFormat);
-                }
-                ParserState::CommentOrEnd => {
-                    return Err(ParserError::InvalidFormat);
+                ParserState::BuildingTags | ParserState::CommentOrEnd => {
+                    return Err(parse_stream.error(ParserError::InvalidFormat));
                 }
             },
         }
@@ -271,10 +352,10 @@ pub fn parse_record(input: &str) -> Result<PronounRecord, ParserError> {
     match parser.state {
         ParserState::BuildingPronounDef { n, trailing_slash } => {
             if parser.def_builder.is_some() && n < 2 {
-                return Err(ParserError::NotEnoughPronounParts);
+                return Err(whole(ParserError::NotEnoughPronounParts));
             }
             if trailing_slash {
-                return Err(ParserError::TrailingSlash);
+                return Err(trailing_slash_error(input, input.len()));
             }
         }
         ParserState::BuildingTags => {}
@@ -282,12 +363,12 @@ pub fn parse_record(input: &str) -> Result<PronounRecord, ParserError> {
     }
 
     if parser.def_builder.is_none() && parser.comment.is_none() {
-        return Err(ParserError::Empty);
+        return Err(whole(ParserError::Empty));
     }
 
     if let Some(PronounSet::Defined { definition, .. }) = parser.def_builder.as_mut() {
         if definition.subject.is_empty() || definition.object.is_empty() {
-            return Err(ParserError::NotEnoughPronounParts);
+            return Err(whole(ParserError::NotEnoughPronounParts));
         }
 
         definition.guess_common();
@@ -301,9 +382,18 @@ pub fn parse_record(input: &str) -> Result<PronounRecord, ParserError> {
     Ok(record)
 }
 
+/// A [`ParserError::TrailingSlash`] at the last `/` before `end`.
+fn trailing_slash_error(input: &str, end: usize) -> SpannedParserError {
+    let start = input[..end].rfind('/').unwrap_or(end);
+    SpannedParserError {
+        error: ParserError::TrailingSlash,
+        span: start..start + 1,
+    }
+}
+
 #[cfg(test)]
 mod parser_tests {
-    use super::{ParserError, PronounSet, PronounTag, parse_record};
+    use super::{ParserError, PronounSet, PronounTag, parse_record, parse_record_spanned};
 
     macro_rules! test_case {
         ($name:ident, $input:expr, $expected_pronoun_set:expr, $expected_comment:expr) => {
@@ -702,6 +792,33 @@ mod parser_tests {
         None
     );
 
+    test_case!(
+        test_language_tag,
+        "er/ihn; preferred; LANG=de-AT",
+        Some(PronounSet::new_defined(
+            "er".to_string(),
+            "ihn".to_string(),
+            None,
+            None,
+            None,
+            vec![
+                PronounTag::Preferred,
+                PronounTag::Language("de-at".to_string())
+            ],
+        )),
+        None
+    );
+    error_case!(
+        test_error_invalid_language_tag,
+        "er/ihn; lang=de_at",
+        ParserError::InvalidTag
+    );
+    error_case!(
+        test_error_empty_language_tag,
+        "er/ihn; lang=",
+        ParserError::InvalidTag
+    );
+
     // test that pronouns with spaces are valid
     test_case!(
         test_edge_case_8,
@@ -773,4 +890,16 @@ mod parser_tests {
         "this\"one/that one",
         ParserError::InvalidFormat
     );
+
+    #[test]
+    fn test_error_spans() {
+        let span = |input| parse_record_spanned(input).unwrap_err().span;
+
+        assert_eq!(span("she/her/"), 7..8);
+        assert_eq!(span("she/her; favourite # hi"), 9..18);
+        assert_eq!(span("* ; preferred # any"), 2..13);
+        assert_eq!(span("he/him/his/his/himself/extra"), 23..28);
+        assert_eq!(span("this\"one/that one"), 4..5);
+        assert_eq!(span("  he  "), 2..4);
+    }
 }
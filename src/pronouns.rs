@@ -1,6 +1,6 @@
 use std::fmt::Display;
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct PronounRecord {
     pub set: Option<PronounSet>,
     pub comment: Option<String>,
@@ -9,6 +9,10 @@ pub struct PronounRecord {
 impl Display for PronounRecord {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         let has_set = match &self.set {
+            Some(pronoun_set) if f.alternate() => {
+                write!(f, "{:#}", pronoun_set)?;
+                true
+            }
             Some(pronoun_set) => {
                 write!(f, "{}", pronoun_set)?;
                 true
@@ -48,7 +52,27 @@ impl Ord for PronounRecord {
     }
 }
 
+/// A record from [`parse_records_with`](crate::parse_records_with), with where it came from.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ParsedRecord {
+    /// Index of the record in the records that were parsed, counting the invalid ones
+    pub index: usize,
+    /// The record, as it was published
+    pub raw: String,
+    pub record: PronounRecord,
+}
+
+/// A record that [`parse_records_with`](crate::parse_records_with) couldn't parse.
 #[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InvalidRecord {
+    /// Index of the record in the records that were parsed
+    pub index: usize,
+    /// The record, as it was published
+    pub raw: String,
+    pub error: crate::ParserError,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub enum PronounSet {
     Defined {
         definition: PronounDef,
@@ -65,14 +89,21 @@ impl Display for PronounSet {
         match self {
             PronounSet::Defined { definition, tags } => {
                 write!(f, "{}/{}", definition.subject, definition.object)?;
-                if let Some(poss_det) = &definition.possessive_determiner() {
-                    write!(f, "/{}", poss_det)?;
-                }
-                if let Some(poss_pron) = &definition.possessive_pronoun() {
-                    write!(f, "/{}", poss_pron)?;
-                }
-                if let Some(reflexive) = &definition.reflexive() {
-                    write!(f, "/{}", reflexive)?;
+                let forms = crate::language::resolve_forms(self.language(), definition);
+                for (_, resolved) in &forms[2..] {
+                    match resolved {
+                        // with `{:#}`, forms that weren't given are marked as inferred
+                        ResolvedForm {
+                            value: Some(value),
+                            provenance,
+                        } if f.alternate() && *provenance != Provenance::Explicit => {
+                            write!(f, "/({})", value)?
+                        }
+                        ResolvedForm {
+                            value: Some(value), ..
+                        } => write!(f, "/{}", value)?,
+                        ResolvedForm { value: None, .. } => {}
+                    }
                 }
                 if !tags.is_empty() {
                     // tags are started and separated by `; `
@@ -98,7 +129,8 @@ impl PartialOrd for PronounSet {
 first should be preferred over non-preferred
 second should be non-any and non-none
 third should be any over none
-fourth should be lexicographical order of subject, then object
+fourth should be lexicographical order of subject, then object, then the other forms and tags,
+so that only equal sets compare as equal
 */
 impl Ord for PronounSet {
     fn cmp(&self, other: &Self) -> std::cmp::Ordering {
@@ -119,10 +151,7 @@ impl Ord for PronounSet {
                 match (a_preferred, b_preferred) {
                     (true, false) => std::cmp::Ordering::Less,
                     (false, true) => std::cmp::Ordering::Greater,
-                    _ => def_a
-                        .subject
-                        .cmp(&def_b.subject)
-                        .then_with(|| def_a.object.cmp(&def_b.object)),
+                    _ => def_a.cmp(def_b).then_with(|| tags_a.cmp(tags_b)),
                 }
             }
             (PronounSet::Defined { .. }, _) => std::cmp::Ordering::Less,
@@ -134,7 +163,7 @@ impl Ord for PronounSet {
     }
 }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
 pub enum CommonPronounDef {
     Masculine,
     Feminine,
@@ -143,7 +172,7 @@ pub enum CommonPronounDef {
 }
 
 impl CommonPronounDef {
-    pub fn subject(&self) -> &str {
+    pub fn subject(&self) -> &'static str {
         match self {
             CommonPronounDef::Masculine => "he",
             CommonPronounDef::Feminine => "she",
@@ -152,7 +181,7 @@ impl CommonPronounDef {
         }
     }
 
-    pub fn object(&self) -> &str {
+    pub fn object(&self) -> &'static str {
         match self {
             CommonPronounDef::Masculine => "him",
             CommonPronounDef::Feminine => "her",
@@ -161,7 +190,7 @@ impl CommonPronounDef {
         }
     }
 
-    pub fn possessive_determiner(&self) -> &str {
+    pub fn possessive_determiner(&self) -> &'static str {
         match self {
             CommonPronounDef::Masculine => "his",
             CommonPronounDef::Feminine => "her",
@@ -170,7 +199,7 @@ impl CommonPronounDef {
         }
     }
 
-    pub fn possessive_pronoun(&self) -> &str {
+    pub fn possessive_pronoun(&self) -> &'static str {
         match self {
             CommonPronounDef::Masculine => "his",
             CommonPronounDef::Feminine => "hers",
@@ -179,7 +208,7 @@ impl CommonPronounDef {
         }
     }
 
-    pub fn reflexive(&self) -> &str {
+    pub fn reflexive(&self) -> &'static str {
         match self {
             CommonPronounDef::Masculine => "himself",
             CommonPronounDef::Feminine => "herself",
@@ -189,7 +218,58 @@ impl CommonPronounDef {
     }
 }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+/// One of the forms of a [`PronounDef`], in the order they appear in a record.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PronounForm {
+    Subject,
+    Object,
+    PossessiveDeterminer,
+    PossessivePronoun,
+    Reflexive,
+}
+
+impl PronounForm {
+    pub const ALL: [PronounForm; 5] = [
+        PronounForm::Subject,
+        PronounForm::Object,
+        PronounForm::PossessiveDeterminer,
+        PronounForm::PossessivePronoun,
+        PronounForm::Reflexive,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            PronounForm::Subject => "subject",
+            PronounForm::Object => "object",
+            PronounForm::PossessiveDeterminer => "possessive_determiner",
+            PronounForm::PossessivePronoun => "possessive_pronoun",
+            PronounForm::Reflexive => "reflexive",
+        }
+    }
+}
+
+/// Where a form of a [`PronounDef`] comes from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Provenance {
+    /// Given in the record
+    Explicit,
+    /// Not given, but taken from the common set that the record matches
+    Inferred(CommonPronounDef),
+    /// Not given, but taken from a common set of the record's language
+    InferredFromLanguage(&'static crate::language::Language),
+    /// Not given, and not known either
+    Unknown,
+}
+
+/// A form of a [`PronounDef`], together with where it comes from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ResolvedForm<'a> {
+    /// `None` only if the form is [`Provenance::Unknown`]
+    pub value: Option<&'a str>,
+    pub provenance: Provenance,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct PronounDef {
     pub subject: String,
     pub object: String,
@@ -200,6 +280,25 @@ pub struct PronounDef {
     common_def: Option<CommonPronounDef>,
 }
 
+impl PartialOrd for PronounDef {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for PronounDef {
+    // by subject, then object, then the forms that were given, sets without a form first
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.subject
+            .cmp(&other.subject)
+            .then_with(|| self.object.cmp(&other.object))
+            .then_with(|| self.possessive_determiner.cmp(&other.possessive_determiner))
+            .then_with(|| self.possessive_pronoun.cmp(&other.possessive_pronoun))
+            .then_with(|| self.reflexive.cmp(&other.reflexive))
+            .then_with(|| self.common_def.cmp(&other.common_def))
+    }
+}
+
 impl PronounDef {
     pub fn new(
         subject: String,
@@ -257,6 +356,51 @@ impl PronounDef {
         self.common_def.as_ref()
     }
 
+    /// A form, and whether it was given or inferred from [`PronounDef::common_def`].
+    ///
+    /// Unlike the accessors like [`PronounDef::reflexive`], this tells forms that someone
+    /// declared apart from guessed ones.
+    pub fn form(&self, form: PronounForm) -> ResolvedForm<'_> {
+        let (explicit, common) = match form {
+            PronounForm::Subject => (Some(&self.subject), None),
+            PronounForm::Object => (Some(&self.object), None),
+            PronounForm::PossessiveDeterminer => (
+                self.possessive_determiner.as_ref(),
+                self.common_def
+                    .map(|common| (common, common.possessive_determiner())),
+            ),
+            PronounForm::PossessivePronoun => (
+                self.possessive_pronoun.as_ref(),
+                self.common_def
+                    .map(|common| (common, common.possessive_pronoun())),
+            ),
+            PronounForm::Reflexive => (
+                self.reflexive.as_ref(),
+                self.common_def.map(|common| (common, common.reflexive())),
+            ),
+        };
+
+        match (explicit, common) {
+            (Some(value), _) => ResolvedForm {
+                value: Some(value),
+                provenance: Provenance::Explicit,
+            },
+            (None, Some((common, value))) => ResolvedForm {
+                value: Some(value),
+                provenance: Provenance::Inferred(common),
+            },
+            (None, None) => ResolvedForm {
+                value: None,
+                provenance: Provenance::Unknown,
+            },
+        }
+    }
+
+    /// Every form, in the order of [`PronounForm::ALL`].
+    pub fn forms(&self) -> [(PronounForm, ResolvedForm<'_>); 5] {
+        PronounForm::ALL.map(|form| (form, self.form(form)))
+    }
+
     pub(crate) fn guess_common(&mut self) {
         // if subject+object match, and rest either match or are None, set common_def
         let common = match (self.subject.as_str(), self.object.as_str()) {
@@ -288,11 +432,14 @@ impl PronounDef {
     }
 }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
 #[non_exhaustive]
 pub enum PronounTag {
     Preferred,
     Plural,
+    /// The language the set is in, as a lowercase language tag like `de` or `es-mx`, written
+    /// `lang=de`. Sets without one are English.
+    Language(String),
 }
 
 impl Display for PronounTag {
@@ -300,6 +447,7 @@ impl Display for PronounTag {
         match self {
             PronounTag::Preferred => write!(f, "preferred"),
             PronounTag::Plural => write!(f, "plural"),
+            PronounTag::Language(code) => write!(f, "lang={}", code),
         }
     }
 }
@@ -308,9 +456,48 @@ impl PronounRecord {
     pub fn new(set: Option<PronounSet>, comment: Option<String>) -> Self {
         PronounRecord { set, comment }
     }
+
+    /// The record as it should be published, with only the parts that were given.
+    ///
+    /// Unlike [`Display`], the forms guessed from a [`CommonPronounDef`] are left out, so
+    /// `she/her` stays `she/her`.
+    pub(crate) fn canonical(&self) -> String {
+        let mut canonical = match &self.set {
+            Some(PronounSet::Defined { definition, tags }) => {
+                let mut parts = vec![definition.subject.as_str(), definition.object.as_str()];
+                parts.extend(
+                    [
+                        &definition.possessive_determiner,
+                        &definition.possessive_pronoun,
+                        &definition.reflexive,
+                    ]
+                    .into_iter()
+                    .map_while(|part| part.as_deref()),
+                );
+
+                let mut canonical = parts.join("/");
+                for tag in tags {
+                    canonical.push_str(&format!("; {}", tag));
+                }
+                canonical
+            }
+            Some(set) => set.to_string(),
+            None => String::new(),
+        };
+
+        if let Some(comment) = &self.comment {
+            if !canonical.is_empty() {
+                canonical.push(' ');
+            }
+            canonical.push_str(&format!("# {}", comment));
+        }
+
+        canonical
+    }
 }
 
 impl PronounSet {
+    /// Prefer [`PronounSet::builder`], which checks that the parts can be published.
     pub fn new_defined(
         subject: String,
         object: String,
@@ -332,6 +519,17 @@ impl PronounSet {
             tags,
         }
     }
+
+    /// The code of the [`PronounTag::Language`] tag, or `None` for English sets, `*` and `!`.
+    pub fn language(&self) -> Option<&str> {
+        match self {
+            PronounSet::Defined { tags, .. } => tags.iter().find_map(|tag| match tag {
+                PronounTag::Language(code) => Some(code.as_str()),
+                _ => None,
+            }),
+            _ => None,
+        }
+    }
 }
 
 impl PronounTag {
@@ -339,11 +537,201 @@ impl PronounTag {
         match string.as_str() {
             "preferred" => Some(PronounTag::Preferred),
             "plural" => Some(PronounTag::Plural),
-            _ => None,
+            _ => {
+                let code = string.strip_prefix("lang=")?;
+                is_language_tag(code).then(|| PronounTag::Language(code.to_string()))
+            }
+        }
+    }
+}
+
+impl PronounSet {
+    /// Starts building a defined set, which [`PronounSetBuilder::build`] checks the same way
+    /// the parser would.
+    ///
+    /// ```
+    /// use podns::PronounSet;
+    ///
+    /// let set = PronounSet::builder("she", "her")
+    ///     .possessive_determiner("her")
+    ///     .preferred()
+    ///     .build()
+    ///     .unwrap();
+    /// assert_eq!(set.to_string(), "she/her/her/hers/herself; preferred");
+    ///
+    /// assert!(PronounSet::builder("she", "her/hers").build().is_err());
+    /// ```
+    pub fn builder(subject: impl Into<String>, object: impl Into<String>) -> PronounSetBuilder {
+        PronounSetBuilder {
+            subject: subject.into(),
+            object: object.into(),
+            possessive_determiner: None,
+            possessive_pronoun: None,
+            reflexive: None,
+            tags: Vec::new(),
         }
     }
 }
 
+/// Why a [`PronounSetBuilder`] can't build a set.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BuildError {
+    /// A form is empty, or only whitespace
+    EmptyForm(PronounForm),
+    /// A form has a character that would end it early in a record
+    InvalidCharacter { form: PronounForm, character: char },
+    /// A form is given without the ones before it, like a reflexive without the possessives
+    MissingForm(PronounForm),
+    /// A tag that the parser wouldn't accept, like a malformed language tag
+    InvalidTag(PronounTag),
+}
+
+impl Display for BuildError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = |form: &PronounForm| form.name().replace('_', " ");
+        match self {
+            BuildError::EmptyForm(form) => write!(f, "the {} is empty", name(form)),
+            BuildError::InvalidCharacter { form, character } => {
+                write!(f, "the {} can't contain `{}`", name(form), character)
+            }
+            BuildError::MissingForm(form) => {
+                write!(f, "the {} is needed before the forms after it", name(form))
+            }
+            BuildError::InvalidTag(tag) => write!(f, "`{}` is not a valid tag", tag),
+        }
+    }
+}
+
+/// Builds a [`PronounSet::Defined`], see [`PronounSet::builder`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PronounSetBuilder {
+    subject: String,
+    object: String,
+    possessive_determiner: Option<String>,
+    possessive_pronoun: Option<String>,
+    reflexive: Option<String>,
+    tags: Vec<PronounTag>,
+}
+
+impl PronounSetBuilder {
+    pub fn possessive_determiner(mut self, possessive_determiner: impl Into<String>) -> Self {
+        self.possessive_determiner = Some(possessive_determiner.into());
+        self
+    }
+
+    pub fn possessive_pronoun(mut self, possessive_pronoun: impl Into<String>) -> Self {
+        self.possessive_pronoun = Some(possessive_pronoun.into());
+        self
+    }
+
+    pub fn reflexive(mut self, reflexive: impl Into<String>) -> Self {
+        self.reflexive = Some(reflexive.into());
+        self
+    }
+
+    pub fn tag(mut self, tag: PronounTag) -> Self {
+        self.tags.push(tag);
+        self
+    }
+
+    pub fn preferred(self) -> Self {
+        self.tag(PronounTag::Preferred)
+    }
+
+    pub fn plural(self) -> Self {
+        self.tag(PronounTag::Plural)
+    }
+
+    /// Tags the set with the language it is in, like `de`.
+    pub fn language(self, code: impl Into<String>) -> Self {
+        self.tag(PronounTag::Language(code.into()))
+    }
+
+    /// Checks every part, and builds the set.
+    ///
+    /// Like the parser, forms are trimmed and lowercased, and duplicate tags are dropped, so
+    /// the set is the same as when its record is parsed.
+    pub fn build(self) -> Result<PronounSet, BuildError> {
+        let forms = [
+            Some(self.subject),
+            Some(self.object),
+            self.possessive_determiner,
+            self.possessive_pronoun,
+            self.reflexive,
+        ];
+
+        let mut parts: [Option<String>; 5] = Default::default();
+        for (i, (form, value)) in PronounForm::ALL.into_iter().zip(forms).enumerate() {
+            let Some(value) = value else {
+                continue;
+            };
+            // forms are positional, so one can't be left out if a later one is given
+            if let Some(missing) = (0..i).find(|&earlier| parts[earlier].is_none()) {
+                return Err(BuildError::MissingForm(PronounForm::ALL[missing]));
+            }
+            parts[i] = Some(check_form(form, &value)?);
+        }
+
+        let mut tags: Vec<PronounTag> = Vec::new();
+        for tag in self.tags {
+            // tags are lowercased when parsed, so `lang=DE` is `lang=de`
+            let parsed = PronounTag::from_string(tag.to_string().to_lowercase())
+                .ok_or(BuildError::InvalidTag(tag))?;
+            if !tags.contains(&parsed) {
+                tags.push(parsed);
+            }
+        }
+
+        let [
+            subject,
+            object,
+            possessive_determiner,
+            possessive_pronoun,
+            reflexive,
+        ] = parts;
+        Ok(PronounSet::new_defined(
+            subject.unwrap_or_default(),
+            object.unwrap_or_default(),
+            possessive_determiner,
+            possessive_pronoun,
+            reflexive,
+            tags,
+        ))
+    }
+}
+
+/// The form as the parser would read it, or why it can't be in a record.
+fn check_form(form: PronounForm, value: &str) -> Result<String, BuildError> {
+    let value = value.trim().to_lowercase();
+
+    if value.is_empty() {
+        return Err(BuildError::EmptyForm(form));
+    }
+    // `*` and `!` at the start of a form are read as any or no pronouns
+    let invalid = value
+        .chars()
+        .enumerate()
+        .find(|&(i, c)| matches!(c, '/' | ';' | '#' | '"') || (i == 0 && matches!(c, '*' | '!')));
+    if let Some((_, character)) = invalid {
+        return Err(BuildError::InvalidCharacter { form, character });
+    }
+
+    Ok(value)
+}
+
+/// Whether `code` looks like a BCP 47 language tag: a primary subtag of 2 to 8 letters,
+/// followed by subtags of 1 to 8 letters or digits, separated by `-`.
+fn is_language_tag(code: &str) -> bool {
+    let mut subtags = code.split('-');
+    let primary = subtags.next().unwrap_or_default();
+
+    (2..=8).contains(&primary.len())
+        && primary.chars().all(|c| c.is_ascii_alphabetic())
+        && subtags.all(|subtag| {
+            (1..=8).contains(&subtag.len()) && subtag.chars().all(|c| c.is_ascii_alphanumeric())
+        })
+}
+
 #[cfg(test)]
 mod tests {
     // test Display implementations
@@ -403,6 +791,96 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_canonical() {
+        let record = PronounRecord::new(
+            Some(PronounSet::new_defined(
+                "she".to_string(),
+                "her".to_string(),
+                None,
+                None,
+                None,
+                vec![PronounTag::Preferred],
+            )),
+            Some("Hi".to_string()),
+        );
+        assert_eq!(record.canonical(), "she/her; preferred # Hi");
+
+        let record = PronounRecord::new(Some(PronounSet::Any), None);
+        assert_eq!(record.canonical(), "*");
+    }
+
+    #[test]
+    fn test_form_provenance() {
+        let def = PronounDef::new(
+            "she".to_string(),
+            "her".to_string(),
+            Some("her".to_string()),
+            None,
+            None,
+        );
+
+        assert_eq!(
+            def.form(PronounForm::PossessiveDeterminer),
+            ResolvedForm {
+                value: Some("her"),
+                provenance: Provenance::Explicit
+            }
+        );
+        assert_eq!(
+            def.form(PronounForm::Reflexive),
+            ResolvedForm {
+                value: Some("herself"),
+                provenance: Provenance::Inferred(CommonPronounDef::Feminine)
+            }
+        );
+
+        let def = PronounDef::new("xe".to_string(), "xem".to_string(), None, None, None);
+        assert_eq!(
+            def.forms().map(|(_, form)| form.provenance),
+            [
+                Provenance::Explicit,
+                Provenance::Explicit,
+                Provenance::Unknown,
+                Provenance::Unknown,
+                Provenance::Unknown,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_display_marks_inferred_forms() {
+        let record = PronounRecord::new(
+            Some(PronounSet::new_defined(
+                "they".to_string(),
+                "them".to_string(),
+                Some("their".to_string()),
+                None,
+                None,
+                vec![PronounTag::Preferred],
+            )),
+            Some("hi".to_string()),
+        );
+
+        assert_eq!(
+            format!("{:#}", record),
+            "they/them/their/(theirs)/(themself); preferred # hi"
+        );
+        assert_eq!(
+            format!("{}", record),
+            "they/them/their/theirs/themself; preferred # hi"
+        );
+
+        // forms inferred from the set's language are marked too
+        let record = crate::parse_record("er/ihn; lang=de").unwrap();
+        assert_eq!(
+            format!("{:#}", record),
+            "er/ihn/(ihm)/(sein)/(seiner); lang=de"
+        );
+        let record = crate::parse_record("she/her; lang=fr").unwrap();
+        assert_eq!(format!("{:#}", record), "she/her; lang=fr");
+    }
+
     #[test]
     fn test_common_def_match() {
         let def = PronounDef::new("she".to_string(), "her".to_string(), None, None, None);
@@ -412,4 +890,126 @@ mod tests {
         assert_eq!(def.possessive_pronoun(), Some("hers"));
         assert_eq!(def.reflexive(), Some("herself"));
     }
+
+    #[test]
+    fn test_ord_consistent_with_eq() {
+        let records = [
+            "he/him",
+            "he/him/his/his/himself",
+            "he/him; plural",
+            "he/him; preferred",
+            "he/him # hi",
+            "*",
+            "!",
+        ]
+        .map(|record| crate::parse_record(record).unwrap());
+
+        for a in &records {
+            for b in &records {
+                assert_eq!(
+                    a.cmp(b) == std::cmp::Ordering::Equal,
+                    a == b,
+                    "{} and {}",
+                    a,
+                    b
+                );
+            }
+        }
+
+        let set: std::collections::BTreeSet<_> = records.iter().cloned().collect();
+        assert_eq!(set.len(), records.len());
+        // preferred sets still come first, and sets with fewer forms before longer ones
+        assert_eq!(
+            set.first().unwrap().to_string(),
+            "he/him/his/his/himself; preferred"
+        );
+        assert_eq!(set.iter().nth(1).unwrap().canonical(), "he/him");
+    }
+
+    #[test]
+    fn test_builder() {
+        let set = PronounSet::builder(" They ", "them")
+            .possessive_determiner("their")
+            .plural()
+            .preferred()
+            .plural()
+            .language("EN")
+            .build()
+            .unwrap();
+        assert_eq!(
+            set,
+            PronounSet::new_defined(
+                "they".to_string(),
+                "them".to_string(),
+                Some("their".to_string()),
+                None,
+                None,
+                vec![
+                    PronounTag::Plural,
+                    PronounTag::Preferred,
+                    PronounTag::Language("en".to_string())
+                ],
+            )
+        );
+
+        // built sets are the same once published and parsed again
+        let record = PronounRecord::new(Some(set), None);
+        assert_eq!(crate::parse_record(&record.canonical()).unwrap(), record);
+    }
+
+    #[test]
+    fn test_builder_errors() {
+        let error = |builder: PronounSetBuilder| builder.build().unwrap_err();
+
+        assert_eq!(
+            error(PronounSet::builder("she", "  ")),
+            BuildError::EmptyForm(PronounForm::Object)
+        );
+        assert_eq!(
+            error(PronounSet::builder("she", "her").reflexive("herself")),
+            BuildError::MissingForm(PronounForm::PossessiveDeterminer)
+        );
+        for character in ['/', ';', '#', '"'] {
+            assert_eq!(
+                error(
+                    PronounSet::builder("she", "her")
+                        .possessive_determiner("her")
+                        .possessive_pronoun("hers")
+                        .reflexive(format!("her{}self", character))
+                ),
+                BuildError::InvalidCharacter {
+                    form: PronounForm::Reflexive,
+                    character
+                }
+            );
+            assert_eq!(
+                error(PronounSet::builder(format!("s{}he", character), "her")),
+                BuildError::InvalidCharacter {
+                    form: PronounForm::Subject,
+                    character
+                }
+            );
+        }
+        assert_eq!(
+            error(PronounSet::builder("*", "her")),
+            BuildError::InvalidCharacter {
+                form: PronounForm::Subject,
+                character: '*'
+            }
+        );
+        // only at the start
+        assert!(PronounSet::builder("s*", "her!").build().is_ok());
+        assert_eq!(
+            error(PronounSet::builder("she", "her").language("not a language")),
+            BuildError::InvalidTag(PronounTag::Language("not a language".to_string()))
+        );
+        assert_eq!(
+            BuildError::InvalidCharacter {
+                form: PronounForm::PossessivePronoun,
+                character: '/'
+            }
+            .to_string(),
+            "the possessive pronoun can't contain `/`"
+        );
+    }
 }
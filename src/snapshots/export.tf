@@ -0,0 +1,21 @@
+resource "aws_route53_record" "pronouns_example_org" {
+  zone_id = var.zone_id
+  name    = "pronouns.example.org"
+  type    = "TXT"
+  ttl     = 3600
+  records = [
+    "ze/hir # long comment long comment long comment long comment long comment long comment long comment long comment long comment long comment long comment long comment long comment long comment long comment long comment long comment long comment long comment\"\" long comment",
+  ]
+}
+
+resource "aws_route53_record" "pronouns_kinda_red" {
+  zone_id = var.zone_id
+  name    = "pronouns.kinda.red"
+  type    = "TXT"
+  ttl     = 3600
+  records = [
+    "she/her; preferred",
+    "they/them # \\\"sometimes\\\", costs $${0}",
+    "sie/sie; lang=de # f\\303\\274r Deutsch",
+  ]
+}
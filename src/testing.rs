@@ -0,0 +1,173 @@
+//! Resolvers for testing code that looks up pronouns, without touching the network.
+//!
+//! ```
+//! use podns::testing::StaticResolver;
+//! use podns::{LookupOptions, lookup_pronouns_for_domain};
+//!
+//! let resolver = StaticResolver::new()
+//!     .with_records("pronouns.example.com", ["she/her"])
+//!     .with_timeout("pronouns.example.net");
+//!
+//! let lookup =
+//!     lookup_pronouns_for_domain(&resolver, "example.com", &LookupOptions::default()).unwrap();
+//! assert_eq!(lookup.records.len(), 1);
+//!
+//! assert!(lookup_pronouns_for_domain(&resolver, "example.net", &LookupOptions::default()).is_err());
+//! ```
+
+use std::collections::HashMap;
+use std::io;
+
+use crate::lookup::{TxtLookup, TxtResolver};
+
+/// What a [`StaticResolver`] answers for a name.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StaticAnswer {
+    Records {
+        records: Vec<String>,
+        authenticated: bool,
+    },
+    /// The name does not exist (NXDOMAIN)
+    NameError,
+    /// The name server did not answer in time
+    Timeout,
+    /// The name server failed to resolve the name (SERVFAIL)
+    ServerFailure,
+}
+
+/// A [`TxtResolver`] that answers from a fixed map of names to TXT records.
+///
+/// Names are matched case-insensitively and without a trailing dot. Names that were not added
+/// do not exist, like they would in DNS. The errors are the same as the ones returned by the
+/// resolvers of this crate, so [`io::ErrorKind::NotFound`] for a name error and
+/// [`io::ErrorKind::TimedOut`] for a timeout.
+#[derive(Debug, Clone, Default)]
+pub struct StaticResolver {
+    answers: HashMap<String, StaticAnswer>,
+}
+
+impl StaticResolver {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Answers `name` with `records`, which are not authenticated with DNSSEC.
+    pub fn with_records<I, S>(self, name: &str, records: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.with_answer(
+            name,
+            StaticAnswer::Records {
+                records: records.into_iter().map(Into::into).collect(),
+                authenticated: false,
+            },
+        )
+    }
+
+    /// Answers `name` with `records`, as if they were validated with DNSSEC.
+    pub fn with_authenticated_records<I, S>(self, name: &str, records: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.with_answer(
+            name,
+            StaticAnswer::Records {
+                records: records.into_iter().map(Into::into).collect(),
+                authenticated: true,
+            },
+        )
+    }
+
+    pub fn with_name_error(self, name: &str) -> Self {
+        self.with_answer(name, StaticAnswer::NameError)
+    }
+
+    pub fn with_timeout(self, name: &str) -> Self {
+        self.with_answer(name, StaticAnswer::Timeout)
+    }
+
+    pub fn with_server_failure(self, name: &str) -> Self {
+        self.with_answer(name, StaticAnswer::ServerFailure)
+    }
+
+    pub fn with_answer(mut self, name: &str, answer: StaticAnswer) -> Self {
+        self.answers.insert(normalize(name), answer);
+        self
+    }
+}
+
+impl TxtResolver for StaticResolver {
+    fn lookup_txt(&self, name: &str) -> io::Result<TxtLookup> {
+        match self
+            .answers
+            .get(&normalize(name))
+            .unwrap_or(&StaticAnswer::NameError)
+        {
+            StaticAnswer::Records {
+                records,
+                authenticated,
+            } => Ok(TxtLookup {
+                name: name.to_owned(),
+                records: records.clone(),
+                authenticated: *authenticated,
+                ttl: None,
+                invalid: Vec::new(),
+            }),
+            StaticAnswer::NameError => Err(io::Error::new(io::ErrorKind::NotFound, "no such name")),
+            StaticAnswer::Timeout => Err(io::Error::from(io::ErrorKind::TimedOut)),
+            StaticAnswer::ServerFailure => Err(io::Error::other("server failure")),
+        }
+    }
+}
+
+fn normalize(name: &str) -> String {
+    name.strip_suffix('.').unwrap_or(name).to_ascii_lowercase()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_records() {
+        let resolver = StaticResolver::new()
+            .with_records("pronouns.example", ["she/her", "they/them"])
+            .with_authenticated_records("pronouns.example.net", ["ze/hir"]);
+
+        let lookup = resolver.lookup_txt("Pronouns.Example.").unwrap();
+        assert_eq!(lookup.name, "Pronouns.Example.");
+        assert_eq!(lookup.records, vec!["she/her", "they/them"]);
+        assert!(!lookup.authenticated);
+
+        assert!(
+            resolver
+                .lookup_txt("pronouns.example.net")
+                .unwrap()
+                .authenticated
+        );
+    }
+
+    #[test]
+    fn test_errors() {
+        let resolver = StaticResolver::new()
+            .with_name_error("pronouns.example")
+            .with_timeout("pronouns.example.net")
+            .with_server_failure("pronouns.example.org");
+
+        let err = resolver.lookup_txt("pronouns.example").unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::NotFound);
+
+        let err = resolver.lookup_txt("pronouns.example.net").unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::TimedOut);
+
+        let err = resolver.lookup_txt("pronouns.example.org").unwrap_err();
+        assert_eq!(err.to_string(), "server failure");
+
+        // names that were never added do not exist
+        let err = resolver.lookup_txt("pronouns.example.com").unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::NotFound);
+    }
+}
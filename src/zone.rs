@@ -0,0 +1,286 @@
+//! Pronoun records to serve or generate zone files for, which can be read from a TOML file
+//! (with the `config` feature) like
+//!
+//! ```toml
+//! # how long resolvers may cache the records, in seconds
+//! ttl = 3600
+//!
+//! [domains]
+//! "kinda.red" = ["she/her; preferred", "they/them"]
+//! "example.org" = "he/him"
+//! ```
+//!
+//! The records of each domain are served at `pronouns.<domain>`.
+
+use std::collections::BTreeMap;
+use std::io;
+#[cfg(feature = "config")]
+use std::path::Path;
+
+use crate::dns::wire;
+use crate::{PRONOUNS_LABEL, parse_record};
+
+const DEFAULT_TTL: u32 = 3600;
+
+/// The pronoun records of a set of domains, each of which is valid according to [`parse_record`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PronounZone {
+    ttl: u32,
+    // keyed by the lowercase domain, without a trailing dot
+    domains: BTreeMap<String, Vec<String>>,
+}
+
+impl Default for PronounZone {
+    fn default() -> Self {
+        PronounZone::new(DEFAULT_TTL)
+    }
+}
+
+impl PronounZone {
+    pub fn new(ttl: u32) -> Self {
+        PronounZone {
+            ttl,
+            domains: BTreeMap::new(),
+        }
+    }
+
+    /// Reads a zone from a TOML file, see the [module documentation](self) for the format.
+    #[cfg(feature = "config")]
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        let path = path.as_ref();
+        let contents = std::fs::read_to_string(path)?;
+
+        PronounZone::from_toml(&contents)
+            .map_err(|e| io::Error::new(e.kind(), format!("{}: {}", path.display(), e)))
+    }
+
+    #[cfg(feature = "config")]
+    pub fn from_toml(contents: &str) -> io::Result<Self> {
+        let table: toml::Table = contents.parse().map_err(invalid)?;
+        let mut zone = PronounZone::default();
+
+        for (key, value) in &table {
+            match (key.as_str(), value) {
+                ("ttl", toml::Value::Integer(ttl)) => {
+                    zone.ttl = u32::try_from(*ttl)
+                        .map_err(|_| invalid(format!("ttl {} is out of range", ttl)))?;
+                }
+                ("domains", toml::Value::Table(domains)) => {
+                    for (domain, records) in domains {
+                        let records = match records {
+                            toml::Value::String(record) => vec![record.clone()],
+                            toml::Value::Array(records) => records
+                                .iter()
+                                .map(|record| match record {
+                                    toml::Value::String(record) => Ok(record.clone()),
+                                    _ => {
+                                        Err(invalid(format!("{}: records must be strings", domain)))
+                                    }
+                                })
+                                .collect::<io::Result<Vec<String>>>()?,
+                            _ => {
+                                return Err(invalid(format!(
+                                    "{}: expected a record or a list of records",
+                                    domain
+                                )));
+                            }
+                        };
+
+                        zone.insert(domain, records)?;
+                    }
+                }
+                ("ttl" | "domains", _) => {
+                    return Err(invalid(format!("{} has the wrong type", key)));
+                }
+                _ => return Err(invalid(format!("unknown key {}", key))),
+            }
+        }
+
+        Ok(zone)
+    }
+
+    /// Adds the records of `domain`, replacing any it already had.
+    ///
+    /// Fails if `domain` is not a valid domain name, or if any of the records is invalid.
+    pub fn insert(&mut self, domain: &str, records: Vec<String>) -> io::Result<()> {
+        let domain = domain.strip_suffix('.').unwrap_or(domain);
+
+        if domain.is_empty() {
+            return Err(invalid("domain is empty"));
+        }
+        wire::write_name(&mut Vec::new(), &format!("{}.{}", PRONOUNS_LABEL, domain))
+            .map_err(|e| invalid(format!("{}: {}", domain, e)))?;
+
+        if records.is_empty() {
+            return Err(invalid(format!("{}: no records", domain)));
+        }
+        for record in &records {
+            parse_record(record).map_err(|e| {
+                invalid(format!("{}: invalid record {:?}: {:?}", domain, record, e))
+            })?;
+        }
+
+        self.domains.insert(domain.to_ascii_lowercase(), records);
+        Ok(())
+    }
+
+    pub fn ttl(&self) -> u32 {
+        self.ttl
+    }
+
+    pub fn set_ttl(&mut self, ttl: u32) {
+        self.ttl = ttl;
+    }
+
+    /// Every domain with its records, ordered by domain.
+    pub fn domains(&self) -> impl Iterator<Item = (&str, &[String])> {
+        self.domains
+            .iter()
+            .map(|(domain, records)| (domain.as_str(), records.as_slice()))
+    }
+
+    /// The records served at `name`, which has to be `pronouns.<domain>` for one of the domains.
+    pub fn records_for_name(&self, name: &str) -> Option<&[String]> {
+        let name = name.strip_suffix('.').unwrap_or(name).to_ascii_lowercase();
+        let domain = name
+            .strip_prefix(PRONOUNS_LABEL)
+            .and_then(|name| name.strip_prefix('.'))?;
+
+        self.domains.get(domain).map(|records| records.as_slice())
+    }
+
+    /// Whether `name` is exactly one of the domains.
+    #[cfg(feature = "dns_server")]
+    pub(crate) fn contains_domain(&self, name: &str) -> bool {
+        let name = name.strip_suffix('.').unwrap_or(name).to_ascii_lowercase();
+        self.domains.contains_key(&name)
+    }
+
+    /// Whether `name` is one of the domains or below one of them.
+    #[cfg(feature = "dns_server")]
+    pub(crate) fn contains_name(&self, name: &str) -> bool {
+        let name = name.strip_suffix('.').unwrap_or(name).to_ascii_lowercase();
+
+        self.domains.keys().any(|domain| {
+            name == *domain
+                || name
+                    .strip_suffix(domain.as_str())
+                    .is_some_and(|prefix| prefix.ends_with('.'))
+        })
+    }
+}
+
+fn invalid(msg: impl ToString) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn zone() -> PronounZone {
+        let mut zone = PronounZone::new(300);
+        zone.insert(
+            "Kinda.Red",
+            vec!["she/her; preferred".to_string(), "they/them".to_string()],
+        )
+        .unwrap();
+        zone.insert("example.org.", vec!["he/him".to_string()])
+            .unwrap();
+        zone
+    }
+
+    #[cfg(feature = "config")]
+    #[test]
+    fn test_from_toml() {
+        let config = r#"
+            ttl = 300
+
+            [domains]
+            "Kinda.Red" = ["she/her; preferred", "they/them"]
+            "example.org." = "he/him"
+        "#;
+
+        assert_eq!(PronounZone::from_toml(config).unwrap(), zone());
+        assert_eq!(PronounZone::from_toml("").unwrap().ttl(), DEFAULT_TTL);
+    }
+
+    #[test]
+    fn test_domains() {
+        assert_eq!(
+            zone().domains().collect::<Vec<_>>(),
+            vec![
+                ("example.org", &["he/him".to_string()][..]),
+                (
+                    "kinda.red",
+                    &["she/her; preferred".to_string(), "they/them".to_string()][..]
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_records_for_name() {
+        let zone = zone();
+        assert_eq!(
+            zone.records_for_name("PRONOUNS.example.org."),
+            Some(&["he/him".to_string()][..])
+        );
+        assert_eq!(zone.records_for_name("example.org"), None);
+        assert_eq!(zone.records_for_name("pronounsexample.org"), None);
+        assert_eq!(zone.records_for_name("pronouns.other.example.org"), None);
+    }
+
+    #[cfg(feature = "dns_server")]
+    #[test]
+    fn test_contains_name() {
+        let zone = zone();
+        assert!(zone.contains_name("example.org"));
+        assert!(zone.contains_name("www.kinda.red"));
+        assert!(!zone.contains_name("notkinda.red"));
+        assert!(zone.contains_domain("Kinda.Red."));
+        assert!(!zone.contains_domain("www.kinda.red"));
+    }
+
+    #[test]
+    fn test_insert_invalid() {
+        let mut zone = PronounZone::default();
+        assert!(zone.insert("", vec!["he/him".to_string()]).is_err());
+        assert!(
+            zone.insert("example..org", vec!["he/him".to_string()])
+                .is_err()
+        );
+        assert!(zone.insert("example.org", vec![]).is_err());
+
+        let err = zone
+            .insert(
+                "example.org",
+                vec![
+                    "he/him".to_string(),
+                    "he/him/his/his/himself/extra".to_string(),
+                ],
+            )
+            .unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert!(err.to_string().contains("example.org"));
+        assert_eq!(zone.domains().count(), 0);
+    }
+
+    #[cfg(feature = "config")]
+    #[test]
+    fn test_invalid_config() {
+        // not TOML
+        assert!(PronounZone::from_toml("domains = [").is_err());
+        // typo in a key
+        assert!(PronounZone::from_toml("tll = 300").is_err());
+        assert!(PronounZone::from_toml("ttl = -1").is_err());
+        assert!(PronounZone::from_toml("[domains]\n\"example.org\" = 3").is_err());
+        assert!(PronounZone::from_toml("[domains]\n\"example.org\" = [1]").is_err());
+        assert!(
+            PronounZone::from_toml(
+                "[domains]\n\"example.org\" = [\"he/him/his/his/himself/extra\"]"
+            )
+            .is_err()
+        );
+    }
+}